@@ -0,0 +1,355 @@
+//! A reusable client for querying a registry index
+
+use std::path::Path;
+
+use tame_index::index::FileLock;
+use tame_index::{IndexKrate, IndexVersion, KrateName, PathBuf};
+
+use crate::rust_versions::RustVersionWalk;
+use crate::walk::WalkOptions;
+use crate::{
+    ComboIndex, CrateSummary, DependencySummary, Error, FeatureSummary, IndexOptions, KeyVersions,
+    Registry, VersionDiff,
+};
+
+/// A client owning an open registry index.
+///
+/// The index, and the HTTP client used to reach it, is set up once when the
+/// client is constructed and reused for every query.
+///
+/// # Examples
+///
+/// ```no_run
+/// use kdeets_lib::Kdeets;
+///
+/// # fn main() -> Result<(), kdeets_lib::Error> {
+/// let kdeets = Kdeets::crates_io()?;
+/// assert!(kdeets.version_exists("serde", "1.0.0")?);
+/// let versions = kdeets.list_versions("serde")?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Kdeets {
+    index: ComboIndex,
+}
+
+impl Kdeets {
+    /// Opens the index of the registry selected by the options
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] if the registry cannot be resolved or its index
+    /// cannot be opened.
+    pub fn new(options: &IndexOptions) -> Result<Self, Error> {
+        Ok(Self {
+            index: options.combo_index()?,
+        })
+    }
+
+    /// Opens the crates.io sparse index
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] if the index cannot be opened.
+    pub fn crates_io() -> Result<Self, Error> {
+        Self::new(&IndexOptions::default())
+    }
+
+    /// Opens the index at the url; sparse indices must carry the `sparse+`
+    /// prefix, other urls are treated as git indices
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] if the index cannot be opened.
+    pub fn from_url(url: impl Into<String>) -> Result<Self, Error> {
+        Self::new(&IndexOptions::new(Registry::Url(url.into())))
+    }
+
+    /// Opens the local registry at the path
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] if the path is not valid UTF-8 or is not a local
+    /// registry.
+    pub fn from_local_registry(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = PathBuf::from_path_buf(path.as_ref().to_path_buf()).map_err(|path| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{} is not valid UTF-8", path.display()),
+            )
+        })?;
+        Self::new(&IndexOptions::new(Registry::Local(path)))
+    }
+
+    /// Returns `true` if the given version of a crate exists in the index, or
+    /// `false` if the crate is found but the version is absent.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::CrateNotFoundOnIndex`] when the crate is absent from the index.
+    pub fn version_exists(&self, crate_name: &str, version: &str) -> Result<bool, Error> {
+        crate::version_exists_in_index(&self.index, crate_name, version)
+    }
+
+    /// Returns all published version strings for a crate.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::CrateNotFoundOnIndex`] when the crate is absent from the index.
+    pub fn list_versions(&self, crate_name: &str) -> Result<Vec<String>, Error> {
+        crate::list_versions_in_index(&self.index, crate_name)
+    }
+
+    /// Returns the earliest, highest normal, highest and most recent versions
+    /// of a crate.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::CrateNotFoundOnIndex`] when the crate is absent from the index.
+    pub fn key_versions(&self, crate_name: &str) -> Result<KeyVersions, Error> {
+        let index_crate = self.krate(crate_name)?;
+
+        Ok(KeyVersions::from(&index_crate))
+    }
+
+    /// Returns the key versions and every published version of a crate.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::CrateNotFoundOnIndex`] when the crate is absent from the index.
+    pub fn summary(&self, crate_name: &str) -> Result<CrateSummary, Error> {
+        let index_crate = self.krate(crate_name)?;
+        Ok(CrateSummary::from(&index_crate))
+    }
+
+    /// Returns the features of a version of a crate, or of its most recent
+    /// version if none is given.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::CrateNotFoundOnIndex`] when the crate is absent from the index
+    /// and [`Error::VersionNotFound`] when the version is absent.
+    pub fn features(
+        &self,
+        crate_name: &str,
+        version: Option<&str>,
+    ) -> Result<FeatureSummary, Error> {
+        let index_crate = self.krate(crate_name)?;
+        let version = select_version(&index_crate, version)?;
+        Ok(FeatureSummary::from(version))
+    }
+
+    /// Returns the dependencies of a version of a crate, or of its most recent
+    /// version if none is given.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::CrateNotFoundOnIndex`] when the crate is absent from the index
+    /// and [`Error::VersionNotFound`] when the version is absent.
+    pub fn dependencies(
+        &self,
+        crate_name: &str,
+        version: Option<&str>,
+    ) -> Result<DependencySummary, Error> {
+        let index_crate = self.krate(crate_name)?;
+        let version = select_version(&index_crate, version)?;
+        Ok(DependencySummary::from(version))
+    }
+
+    /// Compares the dependencies, features and Rust version of two versions of
+    /// a crate.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::CrateNotFoundOnIndex`] when the crate is absent from the index
+    /// and [`Error::VersionNotFound`] when either version is absent.
+    pub fn diff(&self, crate_name: &str, from: &str, to: &str) -> Result<VersionDiff, Error> {
+        let index_crate = self.krate(crate_name)?;
+        let from = select_version(&index_crate, Some(from))?;
+        let to = select_version(&index_crate, Some(to))?;
+        Ok(VersionDiff::new(from, to))
+    }
+
+    /// Returns the Rust version declared by the most recent version of a crate.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::CrateNotFoundOnIndex`] when the crate is absent from the index.
+    pub fn rust_version(&self, crate_name: &str) -> Result<Option<String>, Error> {
+        let index_crate = self.krate(crate_name)?;
+
+        Ok(index_crate
+            .most_recent_version()
+            .rust_version
+            .as_ref()
+            .map(ToString::to_string))
+    }
+
+    /// Returns the highest Rust version declared by the most recent version of
+    /// a crate or anywhere in its dependency graph, or `None` if none of them
+    /// declare one.
+    ///
+    /// Normal and build dependencies are followed, and optional dependencies
+    /// when the default features enable them; dev dependencies are not.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::CrateNotFoundOnIndex`] when the crate or one of its
+    /// dependencies is absent from the index.
+    pub fn minimum_rust_version(&self, crate_name: &str) -> Result<Option<String>, Error> {
+        let index_crate = self.krate(crate_name)?;
+        let version = index_crate.most_recent_version();
+        let mut walk = RustVersionWalk::new(&self.index, WalkOptions::default());
+        walk.walk(&index_crate, version)?;
+
+        Ok(walk
+            .minimum_rust_version(version)
+            .map(|rust_version| rust_version.to_string()))
+    }
+
+    fn krate(&self, crate_name: &str) -> Result<IndexKrate, Error> {
+        let lock = FileLock::unlocked();
+        let index_crate = self
+            .index
+            .krate(KrateName::cargo(crate_name)?, true, &lock)?;
+
+        index_crate.ok_or_else(|| self.index.not_found(crate_name))
+    }
+}
+
+/// Finds the version of a crate, or its most recent version if none is given
+pub(crate) fn select_version<'k>(
+    index_crate: &'k IndexKrate,
+    version: Option<&str>,
+) -> Result<&'k IndexVersion, Error> {
+    let Some(version) = version else {
+        return Ok(index_crate.most_recent_version());
+    };
+
+    index_crate
+        .versions
+        .iter()
+        .find(|index_version| index_version.version == version)
+        .ok_or_else(|| Error::VersionNotFound(index_crate.name().to_string(), version.to_string()))
+}
+
+impl From<ComboIndex> for Kdeets {
+    fn from(index: ComboIndex) -> Self {
+        Self { index }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_client() -> (tempfile::TempDir, Kdeets) {
+        let (temp_dir, registry) = crate::tests::get_temp_local_registry();
+        let kdeets = Kdeets::from_local_registry(&registry).unwrap();
+        (temp_dir, kdeets)
+    }
+
+    #[test]
+    fn test_from_local_registry_invalid_path() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let result = Kdeets::from_local_registry(temp_dir.path().join("missing"));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_key_versions() {
+        let (_temp_dir, kdeets) = get_test_client();
+        let key_versions = kdeets.key_versions("some_crate").unwrap();
+        assert_eq!(
+            key_versions,
+            KeyVersions {
+                earliest: "0.2.1".to_string(),
+                highest_normal: Some("0.2.1".to_string()),
+                highest: "0.2.1".to_string(),
+                most_recent: "0.2.1".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_key_versions_crate_not_on_index() {
+        let (_temp_dir, kdeets) = get_test_client();
+        let result = kdeets.key_versions("not_in_this_registry");
+        assert!(matches!(result, Err(Error::CrateNotFoundOnIndex)));
+    }
+
+    #[test]
+    fn test_summary() {
+        let (_temp_dir, kdeets) = get_test_client();
+        let summary = kdeets.summary("forestry").unwrap();
+        assert_eq!(summary.name, "forestry");
+        assert_eq!(summary.key_versions.highest, "1.4.1");
+        assert_eq!(
+            summary.key_versions,
+            kdeets.key_versions("forestry").unwrap()
+        );
+        assert_eq!(summary.versions.len(), 1);
+        assert!(!summary.versions[0].yanked);
+    }
+
+    #[test]
+    fn test_features() {
+        let (_temp_dir, kdeets) = get_test_client();
+        let features = kdeets.features("forestry", Some("1.4.1")).unwrap();
+        assert_eq!(features.version, "1.4.1");
+        assert_eq!(features.features[0].name, "plain");
+        assert_eq!(kdeets.features("forestry", None).unwrap(), features);
+    }
+
+    #[test]
+    fn test_features_version_not_found() {
+        let (_temp_dir, kdeets) = get_test_client();
+        let result = kdeets.features("forestry", Some("9.9.9"));
+        assert!(matches!(result, Err(Error::VersionNotFound(_, _))));
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let (_temp_dir, kdeets) = get_test_client();
+        let dependencies = kdeets.dependencies("forestry", Some("1.4.1")).unwrap();
+        assert_eq!(dependencies.dependencies.len(), 1);
+        assert_eq!(dependencies.dependencies[0].name, "colored");
+        assert!(matches!(
+            kdeets.dependencies("forestry", Some("0.0.1")),
+            Err(Error::VersionNotFound(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_diff() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        let entry = std::path::Path::new(&registry).join("index/di/ff/diffed");
+        std::fs::create_dir_all(entry.parent().unwrap()).unwrap();
+        std::fs::write(&entry, crate::diff::tests::TWO_VERSIONS).unwrap();
+        let kdeets = Kdeets::from_local_registry(&registry).unwrap();
+
+        let diff = kdeets.diff("diffed", "1.2.0", "1.3.0").unwrap();
+
+        assert_eq!(diff.rust_version.to.as_deref(), Some("1.70"));
+        assert_eq!(diff.dependencies.len(), 4);
+        assert!(matches!(
+            kdeets.diff("diffed", "1.2.0", "1.4.0"),
+            Err(Error::VersionNotFound(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_rust_version_not_specified() {
+        let (_temp_dir, kdeets) = get_test_client();
+        assert_eq!(kdeets.rust_version("forestry").unwrap(), None);
+        assert_eq!(kdeets.minimum_rust_version("forestry").unwrap(), None);
+    }
+
+    #[test]
+    fn test_from_combo_index() {
+        let (_temp_dir, index) = crate::tests::get_test_sparse_index();
+        let kdeets = Kdeets::from(index);
+        assert!(kdeets.version_exists("some_crate", "0.2.1").unwrap());
+        assert!(!kdeets.version_exists("some_crate", "9.9.9").unwrap());
+    }
+}
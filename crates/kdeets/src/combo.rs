@@ -1,16 +1,35 @@
-use tame_index::index::LocalRegistry;
+use std::io::Error as IoError;
+
+use tame_index::external::http::header::ACCEPT_ENCODING;
+use tame_index::index::local::{ValidKrate, builder::Client};
+use tame_index::index::{IndexConfig, LocalRegistry};
 use tame_index::{
-    Error, IndexKrate, KrateName,
+    Error, IndexKrate, KrateName, PathBuf,
     index::{FileLock, RemoteSparseIndex},
 };
+use tame_index::{IndexVersion, external::http::HeaderValue};
+
+pub use git::GitCliIndex;
+pub use http_cache::HttpCache;
 
-/// A wrapper around either a [`RemoteGitIndex`] or [`RemoteSparseIndex`]
+mod git;
+mod http_cache;
+
+/// A wrapper around either a [`GitCliIndex`], [`RemoteSparseIndex`] or
+/// [`LocalRegistry`]
 #[non_exhaustive]
 pub enum ComboIndex {
+    /// A git index
+    Git(GitCliIndex),
     /// An HTTP sparse index
     Sparse(RemoteSparseIndex),
-    /// A local registry
-    Local(LocalRegistry),
+    /// A local registry and the directory it is in
+    Local(LocalRegistry, PathBuf),
+    /// An index answering only from entries already cached on disk, without
+    /// any network access
+    Cached(Box<ComboIndex>),
+    /// A sparse index whose responses are kept in kdeets' own HTTP cache
+    HttpCached(RemoteSparseIndex, HttpCache),
 }
 
 impl ComboIndex {
@@ -27,36 +46,151 @@ impl ComboIndex {
         lock: &FileLock,
     ) -> Result<Option<IndexKrate>, Error> {
         match self {
+            Self::Git(index) => index.krate(name, write_cache_entry, lock),
             Self::Sparse(index) => index.krate(name, write_cache_entry, lock),
-            Self::Local(lr) => lr.cached_krate(name, lock),
+            Self::Local(lr, _) => lr.cached_krate(name, lock),
+            Self::Cached(index) => index.cached_krate(name, lock),
+            Self::HttpCached(index, cache) => cache.krate(index, name, write_cache_entry, lock),
         }
     }
 
     /// Retrieves the cached crate metadata if it exists
     #[inline]
-    #[allow(dead_code)]
     pub fn cached_krate(
         &self,
         name: KrateName<'_>,
         lock: &FileLock,
     ) -> Result<Option<IndexKrate>, Error> {
         match self {
+            Self::Git(index) => index.cached_krate(name, lock),
             Self::Sparse(index) => index.cached_krate(name, lock),
-            Self::Local(lr) => lr.cached_krate(name, lock),
+            Self::Local(lr, _) => lr.cached_krate(name, lock),
+            Self::Cached(index) => index.cached_krate(name, lock),
+            Self::HttpCached(index, _) => index.cached_krate(name, lock),
+        }
+    }
+
+    /// Opens the local registry in the directory
+    pub fn local(path: PathBuf) -> Result<Self, Error> {
+        Ok(Self::Local(LocalRegistry::open(path.clone(), false)?, path))
+    }
+
+    /// Keeps the responses of a sparse index in the HTTP cache; other indices
+    /// are returned unchanged
+    pub fn with_http_cache(self, cache: HttpCache) -> Self {
+        match self {
+            Self::Sparse(index) => Self::HttpCached(index, cache),
+            index => index,
+        }
+    }
+
+    /// Returns `true` if lookups are answered only from cached entries
+    pub fn is_offline(&self) -> bool {
+        matches!(self, Self::Cached(_))
+    }
+
+    /// The error reported when a crate is missing from the index
+    ///
+    /// When offline the crate may well be on the index but has not been cached.
+    pub(crate) fn not_found(&self, name: &str) -> crate::Error {
+        if self.is_offline() {
+            crate::Error::NotCached(name.to_string())
+        } else {
+            crate::Error::CrateNotFoundOnIndex
+        }
+    }
+
+    /// Retrieves the configuration of the index, used to locate `.crate`
+    /// downloads
+    ///
+    /// For a sparse index the `config.json` saved in the local cache when the
+    /// index was opened is read, and only requested from the remote index when
+    /// there is no cached copy. A git index reads it from the fetched head. A
+    /// local registry has no configuration of its own, so one locating the
+    /// `.crate` files in its directory is returned. When offline only the
+    /// cached configuration is read.
+    pub fn index_config(&self) -> Result<IndexConfig, Error> {
+        match self {
+            Self::Cached(index) => match index.as_ref() {
+                Self::Sparse(index) => index.index.index_config(),
+                index => index.index_config(),
+            },
+            Self::Git(index) => index.index_config(),
+            Self::Sparse(index) | Self::HttpCached(index, _) => {
+                if let Ok(config) = index.index.index_config() {
+                    return Ok(config);
+                }
+                let url = format!("{}config.json", index.index.url());
+                log::debug!("Requesting index configuration from {url}");
+                let body = index
+                    .client
+                    .get(url)
+                    .send()
+                    .and_then(|res| res.error_for_status())
+                    .and_then(|res| res.bytes())?;
+                Ok(serde_json::from_slice(&body)?)
+            }
+            Self::Local(_, path) => Ok(IndexConfig {
+                dl: format!("{path}/{{crate}}-{{version}}.crate"),
+                api: None,
+                auth_required: false,
+            }),
+        }
+    }
+
+    /// Downloads the `.crate` file for a version, validating its checksum
+    ///
+    /// Downloads from a sparse index use the index's client, so requests to a
+    /// registry requiring authentication carry the registry token. The
+    /// `.crate` files of a local registry are read from its directory.
+    pub fn download<'iv>(
+        &self,
+        config: &IndexConfig,
+        version: &'iv IndexVersion,
+    ) -> Result<ValidKrate<'iv>, Error> {
+        match self {
+            Self::Sparse(index) | Self::HttpCached(index, _) => {
+                let url = config.download_url(version.name.as_str().try_into()?, &version.version);
+                log::debug!("Downloading {url}");
+                let body = index
+                    .client
+                    .get(url)
+                    // The checksum is of the compressed .crate file
+                    .header(ACCEPT_ENCODING, HeaderValue::from_static("identity"))
+                    .send()?
+                    .error_for_status()?
+                    .bytes()?;
+                ValidKrate::validate(body, version)
+            }
+            Self::Local(_, path) => {
+                let path = path.join(format!("{}-{}.crate", version.name, version.version));
+                log::debug!("Reading {path}");
+                let body = std::fs::read(&path).map_err(|e| Error::IoPath(e, path))?;
+                ValidKrate::validate(body, version)
+            }
+            Self::Git(_) => {
+                let client = Client::build(crate::get_client_builder())?;
+                ValidKrate::download(&client, config, version)
+            }
+            Self::Cached(_) => Err(IoError::other(format!(
+                "unable to download {}-{} in offline mode",
+                version.name, version.version
+            ))
+            .into()),
         }
     }
 }
 
-impl From<RemoteSparseIndex> for ComboIndex {
+impl From<GitCliIndex> for ComboIndex {
     #[inline]
-    fn from(index: RemoteSparseIndex) -> Self {
-        Self::Sparse(index)
+    fn from(index: GitCliIndex) -> Self {
+        Self::Git(index)
     }
 }
 
-impl From<LocalRegistry> for ComboIndex {
+impl From<RemoteSparseIndex> for ComboIndex {
     #[inline]
-    fn from(local: LocalRegistry) -> Self {
-        Self::Local(local)
+    fn from(index: RemoteSparseIndex) -> Self {
+        Self::Sparse(index)
     }
 }
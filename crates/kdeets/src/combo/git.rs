@@ -0,0 +1,235 @@
+use std::ffi::OsStr;
+use std::io::{Error as IoError, ErrorKind};
+use std::process::{Command, Output, Stdio};
+
+use tame_index::index::{FileLock, IndexCache, IndexConfig, IndexLocation};
+use tame_index::{Error, IndexKrate, KrateName, PathBuf};
+
+/// The ref the remote `HEAD` is fetched to, matching cargo
+const HEAD_REF: &str = "refs/remotes/origin/HEAD";
+
+/// A git registry index, fetched and read with the `git` command line tool,
+/// which must be on the `PATH`
+///
+/// tame-index no longer fetches git indices itself, so only the `.cache`
+/// entries are handled through it. The index is kept as a bare repository in
+/// the same location cargo uses and crate entries are written to the shared
+/// `.cache` directory, keyed by the fetched head commit. Repositories using
+/// either SHA-1 or SHA-256 object ids are supported.
+pub struct GitCliIndex {
+    cache: IndexCache,
+    repo: PathBuf,
+    url: String,
+    /// The hex encoded id of the fetched head commit
+    head: Option<String>,
+}
+
+impl GitCliIndex {
+    /// Opens the git index at the location, using any copy of the repository
+    /// that has already been fetched. No network I/O is performed.
+    pub fn new(location: IndexLocation<'_>) -> Result<Self, Error> {
+        let (path, url) = location.into_parts()?;
+        let repo = path.join(".git");
+
+        let mut git_index = Self {
+            cache: IndexCache::at_path(path),
+            repo,
+            url,
+            head: None,
+        };
+        if git_index.repo.exists() {
+            git_index.set_head()?;
+        }
+        Ok(git_index)
+    }
+
+    /// Fetches the head of the remote index, creating the local repository if
+    /// it does not exist yet
+    pub fn fetch(&mut self) -> Result<(), Error> {
+        if !self.repo.exists() {
+            // Cloning takes the object format, SHA-1 or SHA-256, of the remote
+            log::debug!("Cloning git index to {}", self.repo);
+            git_command(
+                None,
+                [
+                    "clone",
+                    "--bare",
+                    "--quiet",
+                    self.url.as_str(),
+                    self.repo.as_str(),
+                ],
+            )?;
+        }
+
+        log::debug!("Fetching git index from {}", self.url);
+        let refspec = format!("+HEAD:{HEAD_REF}");
+        self.git(["fetch", "--quiet", self.url.as_str(), refspec.as_str()])?;
+        self.set_head()
+    }
+
+    /// Retrieves the index metadata for the specified crate name, optionally
+    /// writing a cache entry for it if there was not already an up to date one
+    pub fn krate(
+        &self,
+        name: KrateName<'_>,
+        write_cache_entry: bool,
+        lock: &FileLock,
+    ) -> Result<Option<IndexKrate>, Error> {
+        let Some(head) = self.head.as_deref() else {
+            return Ok(None);
+        };
+        if let Some(krate) = self.cache.cached_krate(name, Some(head), lock)? {
+            return Ok(Some(krate));
+        }
+
+        let Some(blob) = self.read_file(head, &name.relative_path(Some('/')))? else {
+            return Ok(None);
+        };
+        let krate = IndexKrate::from_slice(&blob)?;
+        if write_cache_entry {
+            self.cache.write_to_cache(&krate, head, lock)?;
+        }
+        Ok(Some(krate))
+    }
+
+    /// Retrieves the cached crate metadata if it exists
+    pub fn cached_krate(
+        &self,
+        name: KrateName<'_>,
+        lock: &FileLock,
+    ) -> Result<Option<IndexKrate>, Error> {
+        self.cache.cached_krate(name, self.head.as_deref(), lock)
+    }
+
+    /// Reads the `config.json` at the head of the index
+    pub fn index_config(&self) -> Result<IndexConfig, Error> {
+        let head = self.head.as_deref().unwrap_or(HEAD_REF);
+        let Some(config) = self.read_file(head, "config.json")? else {
+            return Err(
+                IoError::new(ErrorKind::NotFound, "config.json not found in git index").into(),
+            );
+        };
+        Ok(serde_json::from_slice(&config)?)
+    }
+
+    fn set_head(&mut self) -> Result<(), Error> {
+        let output = self.git(["rev-parse", "--verify", &format!("{HEAD_REF}^{{commit}}")])?;
+        let hex = String::from_utf8_lossy(&output.stdout);
+        let hex = object_id(hex.trim())?;
+        log::debug!("Git index head is {hex}");
+        self.head = Some(hex.to_string());
+        Ok(())
+    }
+
+    /// Reads a file at a revision, returning `None` if it does not exist
+    fn read_file(&self, revision: &str, path: &str) -> Result<Option<Vec<u8>>, Error> {
+        let spec = format!("{revision}:{path}");
+        let mut child = Command::new("git")
+            .arg("--git-dir")
+            .arg(&self.repo)
+            .args(["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(git_not_found)?;
+        {
+            use std::io::Write;
+            let mut stdin = child.stdin.take().expect("stdin is piped");
+            writeln!(stdin, "{spec}")?;
+        }
+        let output = child.wait_with_output()?;
+        check_status(&output)?;
+
+        // `<oid> <type> <size>\n<contents>\n` or `<spec> missing\n`
+        let stdout = output.stdout;
+        let Some(newline) = stdout.iter().position(|b| *b == b'\n') else {
+            return Ok(None);
+        };
+        let header = String::from_utf8_lossy(&stdout[..newline]);
+        if header.ends_with(" missing") {
+            return Ok(None);
+        }
+        let size = header
+            .rsplit(' ')
+            .next()
+            .and_then(|size| size.parse::<usize>().ok())
+            .ok_or_else(|| IoError::other(format!("unexpected git output: {header}")))?;
+
+        Ok(stdout
+            .get(newline + 1..newline + 1 + size)
+            .map(<[u8]>::to_vec))
+    }
+
+    fn git<I, S>(&self, args: I) -> Result<Output, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        git_command(Some(&self.repo), args)
+    }
+}
+
+fn git_command<I, S>(repo: Option<&PathBuf>, args: I) -> Result<Output, Error>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let mut command = Command::new("git");
+    if let Some(repo) = repo {
+        command.arg("--git-dir").arg(repo);
+    }
+    let output = command
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(git_not_found)?;
+    check_status(&output)?;
+    Ok(output)
+}
+
+fn check_status(output: &Output) -> Result<(), Error> {
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(IoError::other(format!("git failed: {}", stderr.trim())).into())
+}
+
+/// Reports a missing `git` binary in place of the bare "not found" error
+fn git_not_found(error: IoError) -> IoError {
+    if error.kind() == ErrorKind::NotFound {
+        IoError::new(
+            ErrorKind::NotFound,
+            "the git command line tool is needed for git indices but was not found on the PATH",
+        )
+    } else {
+        error
+    }
+}
+
+/// Checks the commit id is a hex encoded SHA-1 or SHA-256 object id
+fn object_id(hex: &str) -> Result<&str, Error> {
+    if matches!(hex.len(), 40 | 64) && hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        Ok(hex)
+    } else {
+        Err(IoError::other(format!("invalid commit id {hex}")).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_id() {
+        let sha1 = "a".repeat(40);
+        let sha256 = "0123456789abcdef".repeat(4);
+
+        assert_eq!(object_id(&sha1).unwrap(), sha1);
+        assert_eq!(object_id(&sha256).unwrap(), sha256);
+        assert!(object_id(&"a".repeat(39)).is_err());
+        assert!(object_id(&"g".repeat(40)).is_err());
+    }
+}
@@ -0,0 +1,371 @@
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+use tame_index::external::http::header::{
+    ACCEPT, ETAG, HeaderName, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+};
+use tame_index::external::reqwest::StatusCode;
+use tame_index::index::{FileLock, RemoteSparseIndex};
+use tame_index::{Error, IndexKrate, KrateName, Path, PathBuf, SparseIndex};
+
+/// An on-disk cache of sparse index responses owned by kdeets
+///
+/// Entries are keyed by the index url and crate name and keep the `ETag` and
+/// `Last-Modified` headers of the response, so stale entries are revalidated
+/// with a conditional request. Entries younger than the maximum age are
+/// returned without any network access.
+///
+/// Whether each index requires authentication is recorded alongside its
+/// entries, so opening an index within the maximum age needs no request for
+/// its configuration either.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpCache {
+    root: PathBuf,
+    max_age: Option<Duration>,
+}
+
+/// A cached response: the validators sent by the server and the index file
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Entry {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: Vec<u8>,
+}
+
+impl HttpCache {
+    /// Creates a cache rooted at the directory
+    pub fn new(root: PathBuf, max_age: Option<Duration>) -> Self {
+        Self { root, max_age }
+    }
+
+    /// Creates a cache in the kdeets directory of cargo's home
+    pub fn in_cargo_home(max_age: Option<Duration>) -> Result<Self, Error> {
+        let root = tame_index::utils::cargo_home()?
+            .join("kdeets")
+            .join("http-cache");
+        Ok(Self::new(root, max_age))
+    }
+
+    /// Retrieves the index metadata for a crate, from the cache if the entry is
+    /// fresh or the server confirms it has not been modified
+    pub fn krate(
+        &self,
+        index: &RemoteSparseIndex,
+        name: KrateName<'_>,
+        write_cache_entry: bool,
+        lock: &FileLock,
+    ) -> Result<Option<IndexKrate>, Error> {
+        let path = self.entry_path(index, name)?;
+        let entry = Entry::read(&path)?;
+
+        if let Some(entry) = &entry
+            && self.is_fresh(&path)
+        {
+            log::debug!("Using fresh cache entry for {name}");
+            return IndexKrate::from_slice(&entry.body).map(Some);
+        }
+
+        let mut request = index
+            .client
+            .get(index.index.crate_url(name))
+            .header("cargo-protocol", "version=1")
+            .header(ACCEPT, "text/plain");
+        if let Some(entry) = &entry {
+            if let Some(etag) = &entry.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = request.send()?;
+        match (response.status(), entry) {
+            (StatusCode::NOT_MODIFIED, Some(entry)) => {
+                log::debug!("Cache entry for {name} not modified");
+                fs::File::options()
+                    .write(true)
+                    .open(&path)?
+                    .set_modified(SystemTime::now())?;
+                IndexKrate::from_slice(&entry.body).map(Some)
+            }
+            (
+                StatusCode::NOT_FOUND
+                | StatusCode::GONE
+                | StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS,
+                _,
+            ) => {
+                let _ = fs::remove_file(&path);
+                Ok(None)
+            }
+            _ => {
+                let response = response.error_for_status()?;
+                let header = |name: HeaderName| {
+                    response
+                        .headers()
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                        .map(str::to_owned)
+                };
+                let etag = header(ETAG);
+                let last_modified = header(LAST_MODIFIED);
+                let entry = Entry {
+                    etag,
+                    last_modified,
+                    body: response.bytes()?.to_vec(),
+                };
+                let krate = IndexKrate::from_slice(&entry.body)?;
+
+                log::debug!("Caching index entry for {name}");
+                entry.write(&path)?;
+                if write_cache_entry {
+                    // Keep cargo's cache current so offline lookups see the entry
+                    let _ = index
+                        .index
+                        .cache()
+                        .write_to_cache(&krate, &entry.revision(), lock);
+                }
+                Ok(Some(krate))
+            }
+        }
+    }
+
+    /// Whether the index requires authentication, as last recorded, while the
+    /// record is fresh
+    pub(crate) fn auth_required(&self, index: &SparseIndex) -> Option<bool> {
+        let path = self.auth_required_path(index).ok()?;
+        if !self.is_fresh(&path) {
+            return None;
+        }
+        fs::read_to_string(&path).ok()?.trim().parse().ok()
+    }
+
+    /// Records whether the index requires authentication
+    pub(crate) fn set_auth_required(
+        &self,
+        index: &SparseIndex,
+        auth_required: bool,
+    ) -> Result<(), Error> {
+        let path = self.auth_required_path(index)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, auth_required.to_string())?;
+        Ok(())
+    }
+
+    fn entry_path(&self, index: &RemoteSparseIndex, name: KrateName<'_>) -> Result<PathBuf, Error> {
+        Ok(self.index_dir(&index.index)?.join(name.relative_path(None)))
+    }
+
+    /// Kept beside the entries, where no crate name can collide with it
+    fn auth_required_path(&self, index: &SparseIndex) -> Result<PathBuf, Error> {
+        Ok(self.index_dir(index)?.join("auth-required"))
+    }
+
+    fn index_dir(&self, index: &SparseIndex) -> Result<PathBuf, Error> {
+        let dir = tame_index::utils::url_to_local_dir(index.url(), true)?;
+        Ok(self.root.join(dir.dir_name))
+    }
+
+    fn is_fresh(&self, path: &Path) -> bool {
+        let Some(max_age) = self.max_age else {
+            return false;
+        };
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age < max_age)
+    }
+}
+
+impl Entry {
+    /// Reads an entry stored as header lines, a blank line and the body
+    fn read(path: &Path) -> Result<Option<Self>, Error> {
+        let contents = match fs::read(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut entry = Self::default();
+        let mut rest = contents.as_slice();
+        while let Some(end) = rest.iter().position(|b| *b == b'\n') {
+            let line = String::from_utf8_lossy(&rest[..end]).into_owned();
+            rest = &rest[end + 1..];
+            let Some((name, value)) = line.split_once(": ") else {
+                break;
+            };
+            match name {
+                "etag" => entry.etag = Some(value.to_owned()),
+                "last-modified" => entry.last_modified = Some(value.to_owned()),
+                _ => {}
+            }
+        }
+        entry.body = rest.to_vec();
+        Ok(Some(entry))
+    }
+
+    fn write(&self, path: &Path) -> Result<(), Error> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut contents = Vec::with_capacity(self.body.len() + 128);
+        if let Some(etag) = &self.etag {
+            contents.extend_from_slice(format!("etag: {etag}\n").as_bytes());
+        }
+        if let Some(last_modified) = &self.last_modified {
+            contents.extend_from_slice(format!("last-modified: {last_modified}\n").as_bytes());
+        }
+        contents.push(b'\n');
+        contents.extend_from_slice(&self.body);
+        fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// The revision recorded in cargo's cache, preferring the etag as cargo does
+    fn revision(&self) -> String {
+        match (&self.etag, &self.last_modified) {
+            (Some(etag), _) => format!("etag: {etag}"),
+            (None, Some(last_modified)) => format!("last-modified: {last_modified}"),
+            (None, None) => "Unknown".to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::ComboIndex;
+    use crate::tests::{RequestLog, serve_logged_test_registry, test_sparse_location};
+
+    fn get_logged_sparse_index() -> (TempDir, RemoteSparseIndex, RequestLog) {
+        let (url, log) = serve_logged_test_registry(None);
+        let (temp_dir, location) = test_sparse_location(url);
+        let Ok(ComboIndex::Sparse(index)) = crate::get_remote_combo_index(location, None, None)
+        else {
+            panic!("Expected a sparse index");
+        };
+        (temp_dir, index, log)
+    }
+
+    fn get_cache(temp_dir: &TempDir, max_age: Option<Duration>) -> HttpCache {
+        let root = PathBuf::from_path_buf(temp_dir.path().join("http-cache")).unwrap();
+        HttpCache::new(root, max_age)
+    }
+
+    /// The statuses of the responses to requests for index files
+    fn index_statuses(log: &RequestLog) -> Vec<u16> {
+        log.lock()
+            .unwrap()
+            .iter()
+            .filter(|(path, _)| path != "config.json")
+            .map(|(_, status)| *status)
+            .collect()
+    }
+
+    #[test]
+    fn test_stale_entry_is_revalidated() {
+        let (temp_dir, index, log) = get_logged_sparse_index();
+        let cache = get_cache(&temp_dir, None);
+        let lock = FileLock::unlocked();
+        let name = KrateName::cargo("some_crate").unwrap();
+
+        let first = cache.krate(&index, name, true, &lock).unwrap();
+        let second = cache.krate(&index, name, true, &lock).unwrap();
+
+        assert!(first.is_some());
+        assert_eq!(first, second);
+        assert_eq!(index_statuses(&log), vec![200, 304]);
+    }
+
+    #[test]
+    fn test_fresh_entry_skips_request() {
+        let (temp_dir, index, log) = get_logged_sparse_index();
+        let cache = get_cache(&temp_dir, Some(Duration::from_secs(3600)));
+        let lock = FileLock::unlocked();
+        let name = KrateName::cargo("some_crate").unwrap();
+
+        let first = cache.krate(&index, name, true, &lock).unwrap();
+        let second = cache.krate(&index, name, true, &lock).unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(index_statuses(&log), vec![200]);
+    }
+
+    #[test]
+    fn test_cargo_cache_entry_written() {
+        let (temp_dir, index, _log) = get_logged_sparse_index();
+        let cache = get_cache(&temp_dir, None);
+        let lock = FileLock::unlocked();
+        let name = KrateName::cargo("some_crate").unwrap();
+
+        let krate = cache.krate(&index, name, true, &lock).unwrap();
+
+        assert_eq!(index.cached_krate(name, &lock).unwrap(), krate);
+    }
+
+    #[test]
+    fn test_crate_not_on_index() {
+        let (temp_dir, index, log) = get_logged_sparse_index();
+        let cache = get_cache(&temp_dir, Some(Duration::from_secs(3600)));
+        let lock = FileLock::unlocked();
+        let name = KrateName::cargo("not_in_this_registry").unwrap();
+
+        assert_eq!(cache.krate(&index, name, true, &lock).unwrap(), None);
+        assert!(!cache.entry_path(&index, name).unwrap().exists());
+        assert_eq!(index_statuses(&log), vec![404]);
+    }
+
+    #[test]
+    fn test_entry_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = PathBuf::from_path_buf(temp_dir.path().join("so/me/some_crate")).unwrap();
+        let entry = Entry {
+            etag: Some("\"abc\"".to_string()),
+            last_modified: Some("Tue, 15 Nov 1994 08:12:31 GMT".to_string()),
+            body: b"{\"name\":\"some_crate\"}\n".to_vec(),
+        };
+
+        entry.write(&path).unwrap();
+
+        assert_eq!(Entry::read(&path).unwrap(), Some(entry));
+    }
+
+    #[test]
+    fn test_entry_without_validators() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = PathBuf::from_path_buf(temp_dir.path().join("some_crate")).unwrap();
+        let entry = Entry {
+            body: b"body\n".to_vec(),
+            ..Default::default()
+        };
+
+        entry.write(&path).unwrap();
+
+        assert_eq!(Entry::read(&path).unwrap(), Some(entry));
+        assert_eq!(Entry::default().revision(), "Unknown");
+    }
+
+    #[test]
+    fn test_missing_entry() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = PathBuf::from_path_buf(temp_dir.path().join("missing")).unwrap();
+        assert_eq!(Entry::read(&path).unwrap(), None);
+    }
+
+    #[test]
+    fn test_freshness_requires_max_age() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let path = root.join("entry");
+        fs::write(&path, "\n").unwrap();
+
+        assert!(!HttpCache::new(root.clone(), None).is_fresh(&path));
+        assert!(HttpCache::new(root.clone(), Some(Duration::from_secs(60))).is_fresh(&path));
+        assert!(!HttpCache::new(root, Some(Duration::ZERO)).is_fresh(&path));
+    }
+}
@@ -0,0 +1,264 @@
+use crate::diff::describe_dependency;
+use crate::{ChangeKind, DIFF_HEADER, Error, IndexOptions, Kdeets, OutputFormat, VersionDiff};
+
+use clap::Parser;
+use clap_verbosity::Verbosity;
+use colorful::Colorful;
+
+#[derive(Parser, Debug, Default)]
+#[clap(author, about, long_about = None)]
+pub struct CrateDiff {
+    #[clap(flatten)]
+    logging: Verbosity,
+    /// The name of the crate
+    crate_: String,
+    /// The version to compare from
+    from: String,
+    /// The version to compare to
+    to: String,
+
+    #[clap(skip)]
+    output: String,
+}
+
+impl CrateDiff {
+    pub fn run(
+        &mut self,
+        no_colour: bool,
+        options: &IndexOptions,
+        format: OutputFormat,
+    ) -> Result<String, Error> {
+        log::info!(
+            "Comparing versions {} and {} of crate: {}",
+            self.from,
+            self.to,
+            self.crate_
+        );
+        let kdeets = Kdeets::new(options)?;
+        let diff = kdeets.diff(&self.crate_, &self.from, &self.to)?;
+
+        format.render(&diff, || {
+            self.render(&diff, no_colour);
+            Ok(self.output.to_string())
+        })
+    }
+
+    /// Renders the changes between the versions.
+    fn render(&mut self, diff: &VersionDiff, no_colour: bool) {
+        let subject = format!("{} from {} to {}", diff.name, diff.from, diff.to);
+        self.append_header(no_colour, DIFF_HEADER, &subject);
+
+        let rust_version = |rust_version: &Option<String>| {
+            rust_version
+                .clone()
+                .unwrap_or_else(|| "not specified".to_string())
+        };
+        let line = if diff.rust_version.is_changed() {
+            format!(
+                "Rust version: {} → {}",
+                rust_version(&diff.rust_version.from),
+                rust_version(&diff.rust_version.to)
+            )
+        } else {
+            format!(
+                "Rust version: {} (unchanged)",
+                rust_version(&diff.rust_version.to)
+            )
+        };
+        self.output = format!("{}   {}\n", self.output, line);
+
+        if diff.dependencies.is_empty() {
+            self.output.push_str("   No dependency changes\n");
+        } else {
+            self.append_heading(no_colour, "Dependencies:");
+            let name_width = diff
+                .dependencies
+                .iter()
+                .map(|change| change.name.len())
+                .max()
+                .unwrap_or_default();
+            for change in &diff.dependencies {
+                let description = match (&change.from, &change.to) {
+                    (Some(from), Some(to)) => format!(
+                        "{} → {}",
+                        describe_dependency(from),
+                        describe_dependency(to)
+                    ),
+                    (Some(dependency), None) | (None, Some(dependency)) => {
+                        describe_dependency(dependency)
+                    }
+                    (None, None) => String::new(),
+                };
+                self.output = format!(
+                    "{}     {} {:name_width$}  {}\n",
+                    self.output,
+                    marker(change.change, no_colour),
+                    change.name,
+                    description
+                );
+            }
+        }
+
+        if diff.features.is_empty() {
+            self.output.push_str("   No feature changes\n");
+        } else {
+            self.append_heading(no_colour, "Features:");
+            let enables = |enables: &Option<Vec<String>>| {
+                let enables = enables
+                    .iter()
+                    .flatten()
+                    .map(|enable| format!("\"{enable}\""))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("[{enables}]")
+            };
+            for change in &diff.features {
+                let description = match change.change {
+                    ChangeKind::Added => enables(&change.to),
+                    ChangeKind::Removed => enables(&change.from),
+                    ChangeKind::Changed => {
+                        format!("{} → {}", enables(&change.from), enables(&change.to))
+                    }
+                };
+                self.output = format!(
+                    "{}     {} {} = {}\n",
+                    self.output,
+                    marker(change.change, no_colour),
+                    change.name,
+                    description
+                );
+            }
+        }
+    }
+
+    /// Appends a heading for a section of the changes.
+    fn append_heading(&mut self, no_colour: bool, heading: &str) {
+        let heading = if no_colour {
+            heading.to_string()
+        } else {
+            heading.underlined().to_string()
+        };
+        self.output = format!("{}   {}\n", self.output, heading);
+    }
+
+    /// Appends the header to the output.
+    fn append_header(&mut self, no_colour: bool, heading: &str, subject: &str) {
+        let output = format!(
+            "\n {}",
+            if no_colour {
+                format!("{heading} {subject}.")
+            } else {
+                format!("{heading} {}.", subject.cyan()).bold().to_string()
+            }
+        );
+
+        let line = format!(" {}", "🭶".repeat(heading.len() + 2 + subject.len()));
+
+        self.output = format!("{output}\n{line}\n");
+    }
+}
+
+/// The marker of a change: `+` added, `-` removed or `~` changed
+fn marker(change: ChangeKind, no_colour: bool) -> String {
+    let marker = match change {
+        ChangeKind::Added => "+",
+        ChangeKind::Removed => "-",
+        ChangeKind::Changed => "~",
+    };
+    if no_colour {
+        return marker.to_string();
+    }
+    match change {
+        ChangeKind::Added => marker.green().to_string(),
+        ChangeKind::Removed => marker.red().to_string(),
+        ChangeKind::Changed => marker.yellow().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use crate::crate_diff::CrateDiff;
+    use crate::{Error, IndexOptions, OutputFormat, Registry, VersionDiff};
+
+    fn diff() -> VersionDiff {
+        let index_crate = crate::diff::tests::two_versions();
+        VersionDiff::new(&index_crate.versions[0], &index_crate.versions[1])
+    }
+
+    #[test]
+    fn test_parse() {
+        let crate_diff = CrateDiff::try_parse_from(["diff", "forestry", "1.4.0", "1.4.1"]).unwrap();
+        assert_eq!(crate_diff.crate_, "forestry");
+        assert_eq!(crate_diff.from, "1.4.0");
+        assert_eq!(crate_diff.to, "1.4.1");
+
+        let result = CrateDiff::try_parse_from(["diff", "forestry", "1.4.0"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_render_no_colour() {
+        let mut crate_diff = CrateDiff::default();
+
+        crate_diff.render(&diff(), true);
+
+        let expected = format!(
+            r#"
+ Changes to diffed from 1.2.0 to 1.3.0.
+ {}
+   Rust version: 1.60 → 1.70
+   Dependencies:
+     - log      ^0.4
+     ~ serde    ^1.0 (optional) → ^1.0.100 (optional)
+     + tracing  ^0.1
+     ~ winapi   ^0.3 (target: cfg(windows)) → ^0.3 (target: cfg(target_os = "windows"))
+   Features:
+     + new = []
+     - old = []
+     ~ std = [] → ["serde"]
+"#,
+            "🭶".repeat(38)
+        );
+        assert_eq!(crate_diff.output, expected);
+    }
+
+    #[test]
+    fn test_render_no_changes() {
+        let index_crate = crate::diff::tests::two_versions();
+        let mut crate_diff = CrateDiff::default();
+
+        crate_diff.render(
+            &VersionDiff::new(&index_crate.versions[0], &index_crate.versions[0]),
+            true,
+        );
+
+        assert!(crate_diff.output.ends_with(
+            "   Rust version: 1.60 (unchanged)\n   No dependency changes\n   No feature changes\n"
+        ));
+    }
+
+    #[test]
+    fn test_run_local_registry() {
+        let options = IndexOptions::new(Registry::Local(crate::tests::TEST_REGISTRY.into()));
+        let mut crate_diff =
+            CrateDiff::try_parse_from(["diff", "forestry", "1.4.1", "1.4.1"]).unwrap();
+
+        let output = crate_diff.run(true, &options, OutputFormat::Text).unwrap();
+
+        assert!(output.contains("Changes to forestry from 1.4.1 to 1.4.1."));
+        assert!(output.contains("Rust version: not specified (unchanged)"));
+    }
+
+    #[test]
+    fn test_run_version_not_found() {
+        let options = IndexOptions::new(Registry::Local(crate::tests::TEST_REGISTRY.into()));
+        let mut crate_diff =
+            CrateDiff::try_parse_from(["diff", "forestry", "1.4.0", "1.4.1"]).unwrap();
+
+        let result = crate_diff.run(true, &options, OutputFormat::Json);
+
+        assert!(matches!(result, Err(Error::VersionNotFound(_, _))));
+    }
+}
@@ -1,13 +1,16 @@
-use crate::Error;
+use crate::{
+    CrateSummary, DEPENDENCIES_HEADER, DependencyKind, DependencySummary, Error, FEATURES_HEADER,
+    FeatureSummary, HEADER, IndexOptions, Kdeets, OutputFormat, VersionSummary,
+};
 
-use clap::Parser;
+use clap::{ArgGroup, Parser};
 use clap_verbosity::Verbosity;
 use colorful::Colorful;
-use smol_str::SmolStr;
-use tame_index::{IndexKrate, KrateName, index::FileLock};
+use semver::VersionReq;
 
 #[derive(Parser, Debug, Default)]
-#[clap(author, version, about, long_about = None)]
+#[clap(author, about, long_about = None)]
+#[clap(group(ArgGroup::new("details").args(["features", "deps"])))]
 pub struct CrateVersions {
     #[clap(flatten)]
     logging: Verbosity,
@@ -37,100 +40,323 @@ pub struct CrateVersions {
     /// List all versions and key values (equivalent to `-entrl`)
     #[clap(short = 'a', long = "all")]
     all: bool,
+    /// Only list versions matching the semver requirement; with `--bare`, display the highest matching version
+    #[clap(long = "req", value_name = "REQ")]
+    req: Option<VersionReq>,
+    /// Exclude yanked versions
+    #[clap(long = "no-yanked", conflicts_with = "yanked_only")]
+    no_yanked: bool,
+    /// Only include yanked versions
+    #[clap(long = "yanked-only")]
+    yanked_only: bool,
+    /// Exclude pre-release versions
+    #[clap(long = "no-prerelease", conflicts_with = "prerelease_only")]
+    no_prerelease: bool,
+    /// Only include pre-release versions
+    #[clap(long = "prerelease-only")]
+    prerelease_only: bool,
+    /// List the features of the most recent version, what each enables and which are enabled by default
+    #[clap(long = "features")]
+    features: bool,
+    /// List the dependencies of the most recent version, grouped by kind
+    #[clap(long = "deps")]
+    deps: bool,
+    /// The version to list the features or dependencies of
+    #[clap(long = "version", value_name = "VERSION", requires = "details")]
+    version: Option<String>,
 
     #[clap(skip)]
     output: String,
 }
 
 impl CrateVersions {
-    pub fn run(&mut self, no_colour: bool) -> Result<String, Error> {
+    pub fn run(
+        &mut self,
+        no_colour: bool,
+        options: &IndexOptions,
+        format: OutputFormat,
+    ) -> Result<String, Error> {
         log::info!("Getting details for crate: {}", self.crate_);
-        let lock = FileLock::unlocked();
-        let index = crate::get_remote_combo_index()?;
-        let index_crate = index.krate(KrateName::crates_io(&self.crate_)?, true, &lock)?;
+        let kdeets = Kdeets::new(options)?;
+
+        if self.features {
+            let features = kdeets.features(&self.crate_, self.version.as_deref())?;
+            return format.render(&features, || {
+                self.render_features(&features, no_colour);
+                Ok(self.output.to_string())
+            });
+        }
+
+        if self.deps {
+            let dependencies = kdeets.dependencies(&self.crate_, self.version.as_deref())?;
+            return format.render(&dependencies, || {
+                self.render_dependencies(&dependencies, no_colour);
+                Ok(self.output.to_string())
+            });
+        }
+
+        let mut summary = kdeets.summary(&self.crate_)?;
+
+        self.select_versions(&mut summary)?;
+
+        format.render(&summary, || {
+            self.render(&summary, no_colour);
+            Ok(self.output.to_string())
+        })
+    }
+
+    /// Keeps only the versions selected by the filters, recomputing the key
+    /// versions over them.
+    fn select_versions(&self, summary: &mut CrateSummary) -> Result<(), Error> {
+        let filters = self.filters();
+        if filters.is_empty() {
+            return Ok(());
+        }
+
+        *summary = summary
+            .filtered(|version| self.selects(version))
+            .ok_or_else(|| Error::NoMatchingVersion(summary.name.clone(), filters.join(", ")))?;
+        Ok(())
+    }
 
-        let Some(index_crate) = index_crate else {
-            return Err(Error::CrateNotFoundOnIndex);
+    /// Describes the filters selected.
+    fn filters(&self) -> Vec<String> {
+        let mut filters = vec![];
+        if let Some(req) = &self.req {
+            filters.push(format!("requirement {req}"));
+        }
+        if self.no_yanked {
+            filters.push("not yanked".to_string());
+        }
+        if self.yanked_only {
+            filters.push("yanked".to_string());
+        }
+        if self.no_prerelease {
+            filters.push("not a pre-release".to_string());
+        }
+        if self.prerelease_only {
+            filters.push("a pre-release".to_string());
+        }
+        filters
+    }
+
+    /// Returns `true` if the version passes every filter selected.
+    fn selects(&self, version: &VersionSummary) -> bool {
+        let yanked_selected = if version.yanked {
+            !self.no_yanked
+        } else {
+            !self.yanked_only
+        };
+        let prerelease_selected = if version.is_prerelease() {
+            !self.no_prerelease
+        } else {
+            !self.prerelease_only
         };
 
+        self.req.as_ref().is_none_or(|req| version.matches(req))
+            && yanked_selected
+            && prerelease_selected
+    }
+
+    /// Renders the summary of the crate according to the options selected.
+    fn render(&mut self, summary: &CrateSummary, no_colour: bool) {
         if self.bare {
-            self.bare_output(&index_crate);
+            self.bare_output(summary);
         } else {
-            self.append_header(no_colour, index_crate.name());
+            self.append_header(no_colour, HEADER, &summary.name);
 
             if self.earliest | self.all | self.key {
                 let description = "Earliest version";
-                let version = &index_crate.earliest_version().version;
+                let version = &summary.key_versions.earliest;
                 let colour = TextColour::None;
                 self.append_specific_version(description, version, colour);
             }
 
             if self.normal | self.all | self.key {
                 let description = "Highest normal version";
-                let version = &index_crate
-                    .highest_normal_version()
-                    .unwrap_or_else(|| index_crate.highest_version())
-                    .version;
+                let version = summary
+                    .key_versions
+                    .highest_normal
+                    .as_ref()
+                    .unwrap_or(&summary.key_versions.highest);
                 let colour = set_colour(no_colour, TextColour::Blue);
                 self.append_specific_version(description, version, colour);
             }
 
             if self.highest | self.all | self.key {
                 let description = "Highest version";
-                let version = &index_crate.highest_version().version;
+                let version = &summary.key_versions.highest;
                 let colour = set_colour(no_colour, TextColour::Green);
                 self.append_specific_version(description, version, colour);
             }
 
             if self.recent | self.all | self.key {
                 let description = "Most recent version";
-                let version = &index_crate.most_recent_version().version;
+                let version = &summary.key_versions.most_recent;
                 let colour = set_colour(no_colour, TextColour::Yellow);
                 self.append_specific_version(description, version, colour);
             }
 
             if self.list | self.all {
-                self.append_list(index_crate, no_colour);
+                self.append_list(summary, no_colour);
             }
         };
-
-        Ok(self.output.to_string())
     }
 
     /// Sets the output to the bare version string.
-    fn bare_output(&mut self, index_crate: &IndexKrate) {
+    fn bare_output(&mut self, summary: &CrateSummary) {
         self.output = if self.recent {
-            index_crate.most_recent_version().version.to_string()
+            summary.key_versions.most_recent.clone()
         } else if self.highest {
-            index_crate.highest_version().version.to_string()
+            summary.key_versions.highest.clone()
         } else if self.normal {
-            index_crate
-                .highest_normal_version()
-                .unwrap_or_else(|| index_crate.highest_version())
-                .version
-                .to_string()
+            summary
+                .key_versions
+                .highest_normal
+                .clone()
+                .unwrap_or_else(|| summary.key_versions.highest.clone())
+        } else if self.req.is_some() {
+            summary.key_versions.highest.clone()
         } else {
-            index_crate.earliest_version().version.to_string()
+            summary.key_versions.earliest.clone()
         };
     }
 
+    /// Renders the features of the crate version.
+    fn render_features(&mut self, summary: &FeatureSummary, no_colour: bool) {
+        let subject = format!("{} {}", summary.name, summary.version);
+        self.append_header(no_colour, FEATURES_HEADER, &subject);
+
+        if summary.features.is_empty() {
+            self.output.push_str("   No features\n");
+        }
+
+        for feature in &summary.features {
+            let enables = feature
+                .enables
+                .iter()
+                .map(|enable| format!("\"{enable}\""))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let marker = if feature.implicit {
+                Some(" (implicit)")
+            } else if feature.default && feature.name != "default" {
+                Some(" (default)")
+            } else {
+                None
+            };
+            let marker = marker
+                .map(|marker| set_colour(no_colour, TextColour::Blue).paint(marker.to_string()))
+                .unwrap_or_default();
+            self.output = format!(
+                "{}   {} = [{}]{}\n",
+                self.output, feature.name, enables, marker
+            );
+        }
+
+        let feature_only = summary.feature_only_dependencies().collect::<Vec<_>>();
+        if !feature_only.is_empty() {
+            self.output
+                .push_str("   Optional dependencies enabled only through features:\n");
+            for dependency in feature_only {
+                self.output = format!(
+                    "{}     {}: {}\n",
+                    self.output,
+                    dependency.name,
+                    dependency.enabled_by.join(", ")
+                );
+            }
+        }
+    }
+
+    /// Renders the dependencies of the crate version, grouped by kind.
+    fn render_dependencies(&mut self, summary: &DependencySummary, no_colour: bool) {
+        let subject = format!("{} {}", summary.name, summary.version);
+        self.append_header(no_colour, DEPENDENCIES_HEADER, &subject);
+
+        if summary.dependencies.is_empty() {
+            self.output.push_str("   No dependencies\n");
+        }
+
+        let name_width = summary
+            .dependencies
+            .iter()
+            .map(|dependency| dependency.name.len())
+            .max()
+            .unwrap_or_default();
+        let req_width = summary
+            .dependencies
+            .iter()
+            .map(|dependency| dependency.req.len())
+            .max()
+            .unwrap_or_default();
+
+        for kind in [
+            DependencyKind::Normal,
+            DependencyKind::Build,
+            DependencyKind::Dev,
+        ] {
+            let mut dependencies = summary.of_kind(kind).peekable();
+            if dependencies.peek().is_none() {
+                continue;
+            }
+
+            let heading = match kind {
+                DependencyKind::Normal => "Dependencies:",
+                DependencyKind::Build => "Build dependencies:",
+                DependencyKind::Dev => "Dev dependencies:",
+            };
+            let heading = if no_colour {
+                heading.to_string()
+            } else {
+                heading.underlined().to_string()
+            };
+            self.output = format!("{}   {}\n", self.output, heading);
+
+            for dependency in dependencies {
+                let mut details = vec![];
+                if let Some(package) = &dependency.package {
+                    details.push(format!("package: {package}"));
+                }
+                if dependency.optional {
+                    details.push("optional".to_string());
+                }
+                if !dependency.default_features {
+                    details.push("no default features".to_string());
+                }
+                if !dependency.features.is_empty() {
+                    details.push(format!("features: {}", dependency.features.join(", ")));
+                }
+                if let Some(target) = &dependency.target {
+                    details.push(format!("target: {target}"));
+                }
+
+                let line = format!(
+                    "     {:name_width$}  {:req_width$}  {}",
+                    dependency.name,
+                    dependency.req,
+                    details.join(", ")
+                );
+                self.output = format!("{}{}\n", self.output, line.trim_end());
+            }
+        }
+    }
+
     /// Appends the header to the output.
-    fn append_header(&mut self, no_colour: bool, crate_name: &str) {
+    fn append_header(&mut self, no_colour: bool, heading: &str, subject: &str) {
         let output = format!(
             "\n {}",
             if no_colour {
-                format!("Crate versions for {crate_name}.")
+                format!("{heading} {subject}.")
             } else {
-                format!("Crate versions for {}.", crate_name.cyan())
-                    .bold()
-                    .to_string()
+                format!("{heading} {}.", subject.cyan()).bold().to_string()
             }
         );
 
         let mut i = 0;
         let mut line = String::from(" ");
 
-        while i < 20 + crate_name.len() {
+        while i < heading.len() + 2 + subject.len() {
             line.push('🭶');
             i += 1;
         }
@@ -138,23 +364,18 @@ impl CrateVersions {
         self.output = format!("{output}\n{line}\n");
     }
 
-    fn append_specific_version(
-        &mut self,
-        description: &str,
-        version: &SmolStr,
-        colour: TextColour,
-    ) {
+    fn append_specific_version(&mut self, description: &str, version: &str, colour: TextColour) {
         let addition = format!("{description}: {version}");
         let addition = colour.paint(addition);
         self.output = format!("{}   {}\n", self.output, addition)
     }
 
-    fn append_list(&mut self, index_crate: IndexKrate, no_colour: bool) {
+    fn append_list(&mut self, summary: &CrateSummary, no_colour: bool) {
         const BASE_HEADER: &str = " Yanked  Version ";
 
         let mut header = BASE_HEADER.to_string();
 
-        let rows = index_crate
+        let rows = summary
             .versions
             .iter()
             .map(|x| {
@@ -232,7 +453,33 @@ mod tests {
     use colorful::Colorful;
     use rstest::fixture;
 
+    use clap::Parser;
+    use semver::VersionReq;
+
     use crate::crate_versions::CrateVersions;
+    use crate::{
+        CrateSummary, DependencySummary, Error, FeatureSummary, IndexOptions, KeyVersions,
+        OutputFormat, VersionSummary,
+    };
+
+    fn summary() -> CrateSummary {
+        let version = |version: &str, yanked: bool| VersionSummary {
+            version: version.to_string(),
+            yanked,
+            rust_version: None,
+            checksum: String::new(),
+        };
+        CrateSummary {
+            name: "some_crate".to_string(),
+            key_versions: KeyVersions {
+                earliest: "0.1.0".to_string(),
+                highest: "0.3.0-rc.1".to_string(),
+                highest_normal: None,
+                most_recent: "0.3.0-rc.1".to_string(),
+            },
+            versions: vec![version("0.1.0", true), version("0.3.0-rc.1", false)],
+        }
+    }
 
     #[fixture]
     fn header(#[default("some_crate")] name: &str) -> String {
@@ -298,7 +545,7 @@ mod tests {
         assert!(!crate_versions.all);
         assert!(!crate_versions.key);
 
-        let result = crate_versions.run(false);
+        let result = crate_versions.run(false, &IndexOptions::default(), OutputFormat::Text);
         assert!(result.is_ok());
         let output = result.unwrap();
         assert_eq!(output, expected);
@@ -315,7 +562,7 @@ mod tests {
             ..Default::default()
         };
 
-        let result = crate_versions.run(false);
+        let result = crate_versions.run(false, &IndexOptions::default(), OutputFormat::Text);
         assert!(result.is_ok());
         let output = result.unwrap();
         assert_eq!(output, expected);
@@ -332,7 +579,7 @@ mod tests {
             ..Default::default()
         };
 
-        let result = crate_versions.run(false);
+        let result = crate_versions.run(false, &IndexOptions::default(), OutputFormat::Text);
         assert!(result.is_ok());
         let output = result.unwrap();
         assert_eq!(output, expected);
@@ -349,7 +596,7 @@ mod tests {
             ..Default::default()
         };
 
-        let result = crate_versions.run(false);
+        let result = crate_versions.run(false, &IndexOptions::default(), OutputFormat::Text);
         assert!(result.is_ok());
         let output = result.unwrap();
         assert_eq!(output, expected);
@@ -366,7 +613,7 @@ mod tests {
             ..Default::default()
         };
 
-        let result = crate_versions.run(false);
+        let result = crate_versions.run(false, &IndexOptions::default(), OutputFormat::Text);
         assert!(result.is_ok());
         let output = result.unwrap();
         assert_eq!(output, expected);
@@ -391,7 +638,7 @@ mod tests {
             ..Default::default()
         };
 
-        let result = crate_versions.run(false);
+        let result = crate_versions.run(false, &IndexOptions::default(), OutputFormat::Text);
         assert!(result.is_ok());
         let output = result.unwrap();
         println!("Expected:\n`{expected}`\n\nGot:\n`{output}`");
@@ -416,12 +663,297 @@ mod tests {
             ..Default::default()
         };
 
-        let result = crate_versions.run(false);
+        let result = crate_versions.run(false, &IndexOptions::default(), OutputFormat::Text);
         assert!(result.is_ok());
         let output = result.unwrap();
         assert_eq!(output, expected);
     }
 
+    #[test]
+    fn test_render_bare_normal_falls_back_to_highest() {
+        let mut crate_versions = CrateVersions {
+            bare: true,
+            normal: true,
+            ..Default::default()
+        };
+
+        crate_versions.render(&summary(), true);
+
+        assert_eq!(crate_versions.output, "0.3.0-rc.1");
+    }
+
+    #[test]
+    fn test_render_bare_req() {
+        let mut crate_versions = CrateVersions {
+            bare: true,
+            req: Some(VersionReq::parse("^0.1").unwrap()),
+            ..Default::default()
+        };
+        let mut summary = summary();
+
+        crate_versions.select_versions(&mut summary).unwrap();
+        crate_versions.render(&summary, true);
+
+        assert_eq!(crate_versions.output, "0.1.0");
+    }
+
+    #[test]
+    fn test_render_key_no_yanked() {
+        let mut crate_versions = CrateVersions {
+            key: true,
+            no_yanked: true,
+            ..Default::default()
+        };
+        let mut summary = summary();
+
+        crate_versions.select_versions(&mut summary).unwrap();
+        crate_versions.render(&summary, true);
+
+        let expected = format!(
+            "\n Crate versions for some_crate.\n {}\n   Earliest version: 0.3.0-rc.1\n   Highest normal version: 0.3.0-rc.1\n   Highest version: 0.3.0-rc.1\n   Most recent version: 0.3.0-rc.1\n",
+            "🭶".repeat(30)
+        );
+        assert_eq!(crate_versions.output, expected);
+    }
+
+    #[test]
+    fn test_render_list_yanked_only() {
+        let mut crate_versions = CrateVersions {
+            list: true,
+            yanked_only: true,
+            ..Default::default()
+        };
+        let mut summary = summary();
+
+        crate_versions.select_versions(&mut summary).unwrap();
+        crate_versions.render(&summary, true);
+
+        let expected = format!(
+            "\n Crate versions for some_crate.\n {}\n    Yanked  Version \n      Yes     0.1.0\n",
+            "🭶".repeat(30)
+        );
+        assert_eq!(crate_versions.output, expected);
+    }
+
+    #[test]
+    fn test_select_versions_prerelease() {
+        let no_prerelease = CrateVersions {
+            no_prerelease: true,
+            ..Default::default()
+        };
+        let mut stable = summary();
+        no_prerelease.select_versions(&mut stable).unwrap();
+        assert_eq!(stable.key_versions.highest, "0.1.0");
+        assert_eq!(stable.versions.len(), 1);
+
+        let prerelease_only = CrateVersions {
+            prerelease_only: true,
+            no_yanked: true,
+            ..Default::default()
+        };
+        let mut prerelease = summary();
+        prerelease_only.select_versions(&mut prerelease).unwrap();
+        assert_eq!(prerelease.key_versions.earliest, "0.3.0-rc.1");
+        assert_eq!(prerelease.key_versions.highest_normal, None);
+    }
+
+    #[test]
+    fn test_select_versions_no_match_describes_filters() {
+        let crate_versions = CrateVersions {
+            yanked_only: true,
+            prerelease_only: true,
+            ..Default::default()
+        };
+
+        let result = crate_versions.select_versions(&mut summary());
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "No version of some_crate matches: yanked, a pre-release"
+        );
+    }
+
+    fn features_of(path: &str) -> FeatureSummary {
+        let index_crate =
+            tame_index::IndexKrate::new(format!("{}/index/{path}", crate::tests::TEST_REGISTRY))
+                .unwrap();
+        FeatureSummary::from(index_crate.most_recent_version())
+    }
+
+    #[test]
+    fn test_render_features_no_colour() {
+        let mut crate_versions = CrateVersions::default();
+
+        crate_versions.render_features(&features_of("pr/oc/proc-macro2"), true);
+
+        let expected = format!(
+            "\n Features of proc-macro2 1.0.89.\n {}\n   default = [\"proc-macro\"]\n   nightly = []\n   proc-macro = [] (default)\n   span-locations = []\n",
+            "🭶".repeat(31)
+        );
+        assert_eq!(crate_versions.output, expected);
+    }
+
+    #[test]
+    fn test_render_features_feature_only_dependencies() {
+        let mut crate_versions = CrateVersions::default();
+
+        crate_versions.render_features(&features_of("la/zy/lazy_static"), true);
+
+        assert!(crate_versions.output.ends_with(
+            "   spin = [\"dep:spin\"]\n   spin_no_std = [\"spin\"]\n   Optional dependencies enabled only through features:\n     spin: spin\n"
+        ));
+    }
+
+    #[test]
+    fn test_parse_features_version() {
+        let crate_versions = CrateVersions::try_parse_from([
+            "crate",
+            "--features",
+            "--version",
+            "1.4.1",
+            "forestry",
+        ])
+        .unwrap();
+        assert!(crate_versions.features);
+        assert_eq!(crate_versions.version.as_deref(), Some("1.4.1"));
+
+        let result = CrateVersions::try_parse_from(["crate", "--version", "1.4.1", "forestry"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_render_dependencies_no_colour() {
+        let index_crate = tame_index::IndexKrate::new(format!(
+            "{}/index/la/zy/lazy_static",
+            crate::tests::TEST_REGISTRY
+        ))
+        .unwrap();
+        let mut crate_versions = CrateVersions::default();
+
+        crate_versions.render_dependencies(
+            &DependencySummary::from(index_crate.most_recent_version()),
+            true,
+        );
+
+        let expected = format!(
+            "\n Dependencies of lazy_static 1.5.0.\n {}\n   Dependencies:\n     spin         ^0.9.8  optional, no default features, features: once\n   Dev dependencies:\n     doc-comment  ^0.3.1\n     trybuild     ^1\n",
+            "🭶".repeat(34)
+        );
+        assert_eq!(crate_versions.output, expected);
+    }
+
+    #[test]
+    fn test_parse_deps_version() {
+        let crate_versions =
+            CrateVersions::try_parse_from(["crate", "--deps", "--version", "1.4.1", "forestry"])
+                .unwrap();
+        assert!(crate_versions.deps);
+        assert_eq!(crate_versions.version.as_deref(), Some("1.4.1"));
+
+        let result = CrateVersions::try_parse_from(["crate", "--deps", "--features", "forestry"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_filters_conflict() {
+        for args in [
+            ["crate", "--no-yanked", "--yanked-only", "some_crate"],
+            [
+                "crate",
+                "--no-prerelease",
+                "--prerelease-only",
+                "some_crate",
+            ],
+        ] {
+            assert!(CrateVersions::try_parse_from(args).is_err());
+        }
+
+        let crate_versions = CrateVersions::try_parse_from([
+            "crate",
+            "--no-yanked",
+            "--no-prerelease",
+            "some_crate",
+        ])
+        .unwrap();
+        assert!(crate_versions.no_yanked);
+        assert!(crate_versions.no_prerelease);
+    }
+
+    #[test]
+    fn test_parse_req() {
+        let crate_versions =
+            CrateVersions::try_parse_from(["crate", "--list", "--req", ">=1.2, <2", "some_crate"])
+                .unwrap();
+        assert_eq!(
+            crate_versions.req,
+            Some(VersionReq::parse(">=1.2, <2").unwrap())
+        );
+
+        let result = CrateVersions::try_parse_from(["crate", "--req", "not a req", "some_crate"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_run_list_req() {
+        let name = "some_crate";
+        let expected = format!(
+            "{}   \u{1b}[4m Yanked  Version \u{1b}[0m\n      \u{1b}[38;5;2m No\u{1b}[0m     0.1.3\n      \u{1b}[38;5;2m No\u{1b}[0m     0.2.1\n",
+            header(name)
+        );
+
+        let mut crate_versions = CrateVersions {
+            crate_: "some_crate".to_string(),
+            list: true,
+            req: Some(VersionReq::parse(">=0.1.2").unwrap()),
+            ..Default::default()
+        };
+
+        let result = crate_versions.run(false, &IndexOptions::default(), OutputFormat::Text);
+        assert_eq!(result.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_select_versions() {
+        let crate_versions = CrateVersions {
+            req: Some(VersionReq::parse(">=0.3.0-rc.1").unwrap()),
+            ..Default::default()
+        };
+        let mut summary = summary();
+
+        crate_versions.select_versions(&mut summary).unwrap();
+
+        assert_eq!(summary.versions.len(), 1);
+        assert_eq!(summary.versions[0].version, "0.3.0-rc.1");
+    }
+
+    #[test]
+    fn test_select_versions_no_match() {
+        let crate_versions = CrateVersions {
+            req: Some(VersionReq::parse("^9").unwrap()),
+            ..Default::default()
+        };
+
+        let result = crate_versions.select_versions(&mut summary());
+
+        assert!(matches!(result, Err(Error::NoMatchingVersion(_, _))));
+    }
+
+    #[test]
+    fn test_render_all_no_colour() {
+        let mut crate_versions = CrateVersions {
+            all: true,
+            ..Default::default()
+        };
+
+        crate_versions.render(&summary(), true);
+
+        let expected = format!(
+            "\n Crate versions for some_crate.\n {}\n   Earliest version: 0.1.0\n   Highest normal version: 0.3.0-rc.1\n   Highest version: 0.3.0-rc.1\n   Most recent version: 0.3.0-rc.1\n    Yanked  Version \n      Yes     0.1.0\n       No     0.3.0-rc.1\n",
+            "🭶".repeat(30)
+        );
+        assert_eq!(crate_versions.output, expected);
+    }
+
     #[test]
     fn test_run_invalid_crate() {
         let mut crate_versions = CrateVersions {
@@ -429,7 +961,7 @@ mod tests {
             ..Default::default()
         };
 
-        let result = crate_versions.run(false);
+        let result = crate_versions.run(false, &IndexOptions::default(), OutputFormat::Text);
         assert!(result.is_err());
     }
 
@@ -441,7 +973,7 @@ mod tests {
             ..Default::default()
         };
 
-        let result = crate_versions.run(false);
+        let result = crate_versions.run(false, &IndexOptions::default(), OutputFormat::Text);
         assert!(result.is_ok());
     }
 }
@@ -0,0 +1,247 @@
+//! Structured summary of the dependencies of a crate version
+
+use serde::Serialize;
+use tame_index::IndexVersion;
+use tame_index::krate::DependencyKind as IndexDependencyKind;
+
+use crate::Report;
+
+/// The dependencies of a version of a crate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencySummary {
+    /// The name of the crate
+    pub name: String,
+    /// The version of the crate
+    pub version: String,
+    /// Every dependency, grouped by kind in the order normal, build, dev
+    pub dependencies: Vec<Dependency>,
+}
+
+/// A dependency of a crate version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Dependency {
+    /// The name of the dependency in the manifest, which may be a rename
+    pub name: String,
+    /// The name of the crate providing the dependency, when it is renamed
+    pub package: Option<String>,
+    /// The version requirement
+    pub req: String,
+    /// The section of the manifest the dependency is declared in
+    pub kind: DependencyKind,
+    /// Whether the dependency is optional
+    pub optional: bool,
+    /// Whether the default features of the dependency are enabled
+    pub default_features: bool,
+    /// The cfg expression or target the dependency is limited to, if any
+    pub target: Option<String>,
+    /// The features of the dependency requested explicitly
+    pub features: Vec<String>,
+}
+
+/// The section of the manifest a dependency is declared in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyKind {
+    /// `[dependencies]`, used at run time
+    Normal,
+    /// `[build-dependencies]`, used by the build script
+    Build,
+    /// `[dev-dependencies]`, used by tests, examples and benchmarks
+    Dev,
+}
+
+impl DependencySummary {
+    /// Returns the dependencies of the kind
+    pub fn of_kind(&self, kind: DependencyKind) -> impl Iterator<Item = &Dependency> {
+        self.dependencies
+            .iter()
+            .filter(move |dependency| dependency.kind == kind)
+    }
+}
+
+impl From<&IndexVersion> for DependencySummary {
+    fn from(version: &IndexVersion) -> Self {
+        let mut dependencies = version
+            .dependencies()
+            .iter()
+            .map(|dependency| Dependency {
+                name: dependency.name.to_string(),
+                package: dependency.package().map(ToString::to_string),
+                req: dependency.req.to_string(),
+                kind: dependency.kind().into(),
+                optional: dependency.is_optional(),
+                default_features: dependency.has_default_features(),
+                target: dependency.target().map(ToString::to_string),
+                features: dependency.features().to_vec(),
+            })
+            .collect::<Vec<_>>();
+        // A stable sort keeps the order of the index within each kind
+        dependencies.sort_by_key(|dependency| dependency.kind);
+
+        Self {
+            name: version.name.to_string(),
+            version: version.version.to_string(),
+            dependencies,
+        }
+    }
+}
+
+impl From<IndexDependencyKind> for DependencyKind {
+    fn from(kind: IndexDependencyKind) -> Self {
+        match kind {
+            IndexDependencyKind::Normal => Self::Normal,
+            IndexDependencyKind::Build => Self::Build,
+            IndexDependencyKind::Dev => Self::Dev,
+        }
+    }
+}
+
+impl std::fmt::Display for DependencyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Normal => write!(f, "normal"),
+            Self::Build => write!(f, "build"),
+            Self::Dev => write!(f, "dev"),
+        }
+    }
+}
+
+/// A row of the CSV output: a dependency of the crate version
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub(crate) struct DependencyRecord {
+    #[serde(rename = "crate")]
+    name: String,
+    version: String,
+    dependency: String,
+    package: Option<String>,
+    req: String,
+    kind: DependencyKind,
+    optional: bool,
+    default_features: bool,
+    target: Option<String>,
+    features: String,
+}
+
+impl Report for DependencySummary {
+    type Record = DependencyRecord;
+
+    const CSV_HEADERS: &'static [&'static str] = &[
+        "crate",
+        "version",
+        "dependency",
+        "package",
+        "req",
+        "kind",
+        "optional",
+        "default_features",
+        "target",
+        "features",
+    ];
+
+    fn records(&self) -> Vec<Self::Record> {
+        self.dependencies
+            .iter()
+            .map(|dependency| DependencyRecord {
+                name: self.name.clone(),
+                version: self.version.clone(),
+                dependency: dependency.name.clone(),
+                package: dependency.package.clone(),
+                req: dependency.req.clone(),
+                kind: dependency.kind,
+                optional: dependency.optional,
+                default_features: dependency.default_features,
+                target: dependency.target.clone(),
+                features: dependency.features.join(" "),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tame_index::IndexKrate;
+
+    use super::*;
+
+    fn dependencies_of(path: &str) -> DependencySummary {
+        let index_crate =
+            IndexKrate::new(format!("{}/index/{path}", crate::tests::TEST_REGISTRY)).unwrap();
+        DependencySummary::from(index_crate.most_recent_version())
+    }
+
+    #[test]
+    fn test_dependencies_grouped_by_kind() {
+        let summary = dependencies_of("3/s/syn");
+
+        let kinds = summary
+            .dependencies
+            .iter()
+            .map(|dependency| dependency.kind)
+            .collect::<Vec<_>>();
+        let mut sorted = kinds.clone();
+        sorted.sort();
+        assert_eq!(kinds, sorted);
+
+        let normal = summary
+            .of_kind(DependencyKind::Normal)
+            .map(|dependency| dependency.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(normal, vec!["proc-macro2", "quote", "unicode-ident"]);
+        assert_eq!(summary.of_kind(DependencyKind::Dev).count(), 12);
+    }
+
+    #[test]
+    fn test_dependency_details() {
+        let summary = dependencies_of("3/s/syn");
+
+        let quote = &summary.dependencies[1];
+        assert_eq!(
+            quote,
+            &Dependency {
+                name: "quote".to_string(),
+                package: None,
+                req: "^1.0".to_string(),
+                kind: DependencyKind::Normal,
+                optional: true,
+                default_features: false,
+                target: None,
+                features: vec![],
+            }
+        );
+        let reqwest = summary
+            .of_kind(DependencyKind::Dev)
+            .find(|dependency| dependency.name == "reqwest")
+            .unwrap();
+        assert_eq!(reqwest.features, vec!["blocking".to_string()]);
+    }
+
+    #[test]
+    fn test_records() {
+        let summary = dependencies_of("fo/re/forestry");
+
+        assert_eq!(
+            summary.records(),
+            vec![DependencyRecord {
+                name: "forestry".to_string(),
+                version: "1.4.1".to_string(),
+                dependency: "colored".to_string(),
+                package: None,
+                req: "^2.1.0".to_string(),
+                kind: DependencyKind::Normal,
+                optional: false,
+                default_features: true,
+                target: None,
+                features: String::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_kind_serialised_lowercase() {
+        assert_eq!(
+            serde_json::to_string(&DependencyKind::Build).unwrap(),
+            "\"build\""
+        );
+        assert_eq!(DependencyKind::Dev.to_string(), "dev");
+    }
+}
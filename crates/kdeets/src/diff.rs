@@ -0,0 +1,518 @@
+//! Structured comparison of two versions of a crate
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use tame_index::IndexVersion;
+
+use crate::{Dependency, DependencyKind, DependencySummary, FeatureSummary, Report};
+
+/// The changes to the dependencies, features and Rust version of a crate
+/// between two of its versions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VersionDiff {
+    /// The name of the crate
+    pub name: String,
+    /// The version compared from
+    pub from: String,
+    /// The version compared to
+    pub to: String,
+    /// The Rust version declared by each of the versions
+    pub rust_version: RustVersionChange,
+    /// The dependencies added, removed or changed, by kind and then by name
+    pub dependencies: Vec<DependencyChange>,
+    /// The features added, removed or changed, by name
+    pub features: Vec<FeatureChange>,
+}
+
+/// The Rust version declared by each of the versions compared, if any
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RustVersionChange {
+    /// The Rust version declared by the version compared from
+    pub from: Option<String>,
+    /// The Rust version declared by the version compared to
+    pub to: Option<String>,
+}
+
+/// How an entry differs between the versions compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// Only in the version compared to
+    Added,
+    /// Only in the version compared from
+    Removed,
+    /// In both versions, with different details
+    Changed,
+}
+
+/// A dependency added, removed or changed between the versions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencyChange {
+    /// The name of the dependency in the manifest
+    pub name: String,
+    /// How the dependency differs
+    pub change: ChangeKind,
+    /// The details that differ for a changed dependency: `req`, `kind`,
+    /// `optional`, `target`, `package`, `default_features` or `features`
+    pub fields: Vec<String>,
+    /// The dependency in the version compared from, unless it was added
+    pub from: Option<Dependency>,
+    /// The dependency in the version compared to, unless it was removed
+    pub to: Option<Dependency>,
+}
+
+/// A feature added, removed or changed between the versions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeatureChange {
+    /// The name of the feature
+    pub name: String,
+    /// How the feature differs
+    pub change: ChangeKind,
+    /// What the feature enables in the version compared from, unless it was
+    /// added
+    pub from: Option<Vec<String>>,
+    /// What the feature enables in the version compared to, unless it was
+    /// removed
+    pub to: Option<Vec<String>>,
+}
+
+impl VersionDiff {
+    /// Compares two versions of a crate
+    pub fn new(from: &IndexVersion, to: &IndexVersion) -> Self {
+        Self {
+            name: to.name.to_string(),
+            from: from.version.to_string(),
+            to: to.version.to_string(),
+            rust_version: RustVersionChange {
+                from: from.rust_version.as_ref().map(ToString::to_string),
+                to: to.rust_version.as_ref().map(ToString::to_string),
+            },
+            dependencies: dependency_changes(
+                DependencySummary::from(from).dependencies,
+                DependencySummary::from(to).dependencies,
+            ),
+            features: feature_changes(&FeatureSummary::from(from), &FeatureSummary::from(to)),
+        }
+    }
+
+    /// Returns `true` if nothing compared differs between the versions
+    pub fn is_empty(&self) -> bool {
+        !self.rust_version.is_changed() && self.dependencies.is_empty() && self.features.is_empty()
+    }
+}
+
+impl RustVersionChange {
+    /// Returns `true` if the versions declare different Rust versions
+    pub fn is_changed(&self) -> bool {
+        self.from != self.to
+    }
+}
+
+impl DependencyChange {
+    /// The section of the manifest the dependency is declared in, by the
+    /// version compared to when it is in both
+    pub fn kind(&self) -> DependencyKind {
+        self.to
+            .as_ref()
+            .or(self.from.as_ref())
+            .map(|dependency| dependency.kind)
+            .unwrap_or(DependencyKind::Normal)
+    }
+}
+
+impl std::fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Added => write!(f, "added"),
+            Self::Removed => write!(f, "removed"),
+            Self::Changed => write!(f, "changed"),
+        }
+    }
+}
+
+/// Pairs up the dependencies of the two versions and reports those that
+/// differ.
+///
+/// A crate may be declared more than once, for example as a normal and as a
+/// dev dependency, so declarations of the same kind and target are paired
+/// first and only what remains is paired by name.
+fn dependency_changes(from: Vec<Dependency>, to: Vec<Dependency>) -> Vec<DependencyChange> {
+    let mut removed = from;
+    let mut added = to;
+    let mut pairs = vec![];
+
+    let same_declaration = |a: &Dependency, b: &Dependency| {
+        a.name == b.name && a.kind == b.kind && a.target == b.target
+    };
+    let same_name = |a: &Dependency, b: &Dependency| a.name == b.name;
+
+    for same in [
+        &same_declaration as &dyn Fn(&Dependency, &Dependency) -> bool,
+        &same_name,
+    ] {
+        let mut i = 0;
+        while i < removed.len() {
+            match added
+                .iter()
+                .position(|dependency| same(&removed[i], dependency))
+            {
+                Some(j) => pairs.push((removed.remove(i), added.remove(j))),
+                None => i += 1,
+            }
+        }
+    }
+
+    let mut changes = pairs
+        .into_iter()
+        .filter_map(|(from, to)| {
+            let fields = changed_fields(&from, &to);
+            (!fields.is_empty()).then(|| DependencyChange {
+                name: to.name.clone(),
+                change: ChangeKind::Changed,
+                fields,
+                from: Some(from),
+                to: Some(to),
+            })
+        })
+        .chain(added.into_iter().map(|dependency| DependencyChange {
+            name: dependency.name.clone(),
+            change: ChangeKind::Added,
+            fields: vec![],
+            from: None,
+            to: Some(dependency),
+        }))
+        .chain(removed.into_iter().map(|dependency| DependencyChange {
+            name: dependency.name.clone(),
+            change: ChangeKind::Removed,
+            fields: vec![],
+            from: Some(dependency),
+            to: None,
+        }))
+        .collect::<Vec<_>>();
+    changes.sort_by(|a, b| (a.kind(), &a.name).cmp(&(b.kind(), &b.name)));
+    changes
+}
+
+/// Names the details that differ between two declarations of a dependency
+fn changed_fields(from: &Dependency, to: &Dependency) -> Vec<String> {
+    [
+        ("req", from.req != to.req),
+        ("kind", from.kind != to.kind),
+        ("optional", from.optional != to.optional),
+        ("target", from.target != to.target),
+        ("package", from.package != to.package),
+        (
+            "default_features",
+            from.default_features != to.default_features,
+        ),
+        ("features", from.features != to.features),
+    ]
+    .into_iter()
+    .filter(|(_, changed)| *changed)
+    .map(|(field, _)| field.to_string())
+    .collect()
+}
+
+/// Compares what each feature enables, including the implicit features of
+/// optional dependencies
+fn feature_changes(from: &FeatureSummary, to: &FeatureSummary) -> Vec<FeatureChange> {
+    let enables = |summary: &FeatureSummary| {
+        summary
+            .features
+            .iter()
+            .map(|feature| (feature.name.clone(), feature.enables.clone()))
+            .collect::<BTreeMap<_, _>>()
+    };
+    let mut from = enables(from);
+    let to = enables(to);
+
+    let mut changes = vec![];
+    for (name, enables) in to {
+        match from.remove(&name) {
+            Some(previous) if previous == enables => {}
+            previous => changes.push(FeatureChange {
+                name,
+                change: match previous {
+                    Some(_) => ChangeKind::Changed,
+                    None => ChangeKind::Added,
+                },
+                from: previous,
+                to: Some(enables),
+            }),
+        }
+    }
+    changes.extend(from.into_iter().map(|(name, enables)| FeatureChange {
+        name,
+        change: ChangeKind::Removed,
+        from: Some(enables),
+        to: None,
+    }));
+    changes.sort_by(|a, b| a.name.cmp(&b.name));
+    changes
+}
+
+/// Describes a dependency on one line: its requirement followed by whatever
+/// differs from a plain normal dependency
+pub(crate) fn describe_dependency(dependency: &Dependency) -> String {
+    let mut details = vec![];
+    if dependency.kind != DependencyKind::Normal {
+        details.push(dependency.kind.to_string());
+    }
+    if let Some(package) = &dependency.package {
+        details.push(format!("package: {package}"));
+    }
+    if dependency.optional {
+        details.push("optional".to_string());
+    }
+    if !dependency.default_features {
+        details.push("no default features".to_string());
+    }
+    if !dependency.features.is_empty() {
+        details.push(format!("features: {}", dependency.features.join(" ")));
+    }
+    if let Some(target) = &dependency.target {
+        details.push(format!("target: {target}"));
+    }
+
+    if details.is_empty() {
+        dependency.req.clone()
+    } else {
+        format!("{} ({})", dependency.req, details.join(", "))
+    }
+}
+
+/// A row of the CSV output: a change to the Rust version, a dependency or a
+/// feature
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub(crate) struct ChangeRecord {
+    #[serde(rename = "crate")]
+    name: String,
+    from: String,
+    to: String,
+    section: &'static str,
+    item: String,
+    change: ChangeKind,
+    fields: String,
+    before: Option<String>,
+    after: Option<String>,
+}
+
+impl Report for VersionDiff {
+    type Record = ChangeRecord;
+
+    const CSV_HEADERS: &'static [&'static str] = &[
+        "crate", "from", "to", "section", "item", "change", "fields", "before", "after",
+    ];
+
+    fn records(&self) -> Vec<Self::Record> {
+        let record = |section, item: &str, change, fields: String, before, after| ChangeRecord {
+            name: self.name.clone(),
+            from: self.from.clone(),
+            to: self.to.clone(),
+            section,
+            item: item.to_string(),
+            change,
+            fields,
+            before,
+            after,
+        };
+
+        let mut records = vec![];
+        if self.rust_version.is_changed() {
+            let change = match (&self.rust_version.from, &self.rust_version.to) {
+                (None, _) => ChangeKind::Added,
+                (_, None) => ChangeKind::Removed,
+                _ => ChangeKind::Changed,
+            };
+            records.push(record(
+                "rust_version",
+                "",
+                change,
+                String::new(),
+                self.rust_version.from.clone(),
+                self.rust_version.to.clone(),
+            ));
+        }
+        records.extend(self.dependencies.iter().map(|dependency| {
+            record(
+                "dependency",
+                &dependency.name,
+                dependency.change,
+                dependency.fields.join(" "),
+                dependency.from.as_ref().map(describe_dependency),
+                dependency.to.as_ref().map(describe_dependency),
+            )
+        }));
+        records.extend(self.features.iter().map(|feature| {
+            record(
+                "feature",
+                &feature.name,
+                feature.change,
+                String::new(),
+                feature.from.as_ref().map(|enables| enables.join(" ")),
+                feature.to.as_ref().map(|enables| enables.join(" ")),
+            )
+        }));
+        records
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use tame_index::IndexKrate;
+
+    use super::*;
+
+    /// Two versions of a crate, written as index entries, covering each kind
+    /// of change
+    pub(crate) const TWO_VERSIONS: &str = concat!(
+        r#"{"name":"diffed","vers":"1.2.0","deps":["#,
+        r#"{"name":"log","req":"^0.4","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal"},"#,
+        r#"{"name":"serde","req":"^1.0","features":[],"optional":true,"default_features":true,"target":null,"kind":"normal"},"#,
+        r#"{"name":"serde","req":"^1.0","features":["derive"],"optional":false,"default_features":true,"target":null,"kind":"dev"},"#,
+        r#"{"name":"winapi","req":"^0.3","features":[],"optional":false,"default_features":true,"target":"cfg(windows)","kind":"normal"}"#,
+        r#"],"cksum":"0000000000000000000000000000000000000000000000000000000000000000","#,
+        r#""features":{"default":["std"],"std":[],"old":[]},"yanked":false,"rust_version":"1.60"}"#,
+        "\n",
+        r#"{"name":"diffed","vers":"1.3.0","deps":["#,
+        r#"{"name":"serde","req":"^1.0.100","features":[],"optional":true,"default_features":true,"target":null,"kind":"normal"},"#,
+        r#"{"name":"serde","req":"^1.0","features":["derive"],"optional":false,"default_features":true,"target":null,"kind":"dev"},"#,
+        r#"{"name":"tracing","req":"^0.1","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal"},"#,
+        r#"{"name":"winapi","req":"^0.3","features":[],"optional":false,"default_features":true,"target":"cfg(target_os = \"windows\")","kind":"normal"}"#,
+        r#"],"cksum":"0000000000000000000000000000000000000000000000000000000000000000","#,
+        r#""features":{"default":["std"],"std":["serde"],"new":[]},"yanked":false,"rust_version":"1.70"}"#,
+        "\n",
+    );
+
+    pub(crate) fn two_versions() -> IndexKrate {
+        IndexKrate::from_slice(TWO_VERSIONS.as_bytes()).unwrap()
+    }
+
+    fn diff() -> VersionDiff {
+        let index_crate = two_versions();
+        VersionDiff::new(&index_crate.versions[0], &index_crate.versions[1])
+    }
+
+    #[test]
+    fn test_rust_version_change() {
+        let diff = diff();
+
+        assert_eq!(diff.name, "diffed");
+        assert_eq!(diff.from, "1.2.0");
+        assert_eq!(diff.to, "1.3.0");
+        assert_eq!(diff.rust_version.from.as_deref(), Some("1.60"));
+        assert_eq!(diff.rust_version.to.as_deref(), Some("1.70"));
+        assert!(diff.rust_version.is_changed());
+    }
+
+    #[test]
+    fn test_dependency_changes() {
+        let changes = diff()
+            .dependencies
+            .into_iter()
+            .map(|change| (change.name, change.change, change.fields))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            changes,
+            vec![
+                ("log".to_string(), ChangeKind::Removed, vec![]),
+                (
+                    "serde".to_string(),
+                    ChangeKind::Changed,
+                    vec!["req".to_string()]
+                ),
+                ("tracing".to_string(), ChangeKind::Added, vec![]),
+                (
+                    "winapi".to_string(),
+                    ChangeKind::Changed,
+                    vec!["target".to_string()]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dependency_kind_change() {
+        let index_crate = two_versions();
+        let json = serde_json::to_string(&index_crate.versions[0])
+            .unwrap()
+            .replace(r#""kind":"dev""#, r#""kind":"build""#);
+        let from: IndexVersion = serde_json::from_str(&json).unwrap();
+
+        let diff = VersionDiff::new(&from, &index_crate.versions[1]);
+
+        let serde = diff
+            .dependencies
+            .iter()
+            .filter(|change| change.name == "serde")
+            .collect::<Vec<_>>();
+        assert_eq!(serde.len(), 2);
+        assert_eq!(serde[1].kind(), DependencyKind::Dev);
+        assert_eq!(serde[1].fields, vec!["kind".to_string()]);
+    }
+
+    #[test]
+    fn test_feature_changes() {
+        let changes = diff()
+            .features
+            .into_iter()
+            .map(|change| (change.name, change.change))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            changes,
+            vec![
+                ("new".to_string(), ChangeKind::Added),
+                ("old".to_string(), ChangeKind::Removed),
+                ("std".to_string(), ChangeKind::Changed),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_same_version_is_empty() {
+        let index_crate = two_versions();
+
+        let diff = VersionDiff::new(&index_crate.versions[1], &index_crate.versions[1]);
+
+        assert!(diff.is_empty());
+        assert!(diff.records().is_empty());
+    }
+
+    #[test]
+    fn test_describe_dependency() {
+        let diff = diff();
+        let winapi = diff.dependencies[3].to.as_ref().unwrap();
+
+        assert_eq!(
+            describe_dependency(winapi),
+            r#"^0.3 (target: cfg(target_os = "windows"))"#
+        );
+    }
+
+    #[test]
+    fn test_records() {
+        let records = diff().records();
+
+        assert_eq!(records.len(), 8);
+        assert_eq!(
+            records[0],
+            ChangeRecord {
+                name: "diffed".to_string(),
+                from: "1.2.0".to_string(),
+                to: "1.3.0".to_string(),
+                section: "rust_version",
+                item: String::new(),
+                change: ChangeKind::Changed,
+                fields: String::new(),
+                before: Some("1.60".to_string()),
+                after: Some("1.70".to_string()),
+            }
+        );
+        assert_eq!(records[2].item, "serde");
+        assert_eq!(records[2].before.as_deref(), Some("^1.0 (optional)"));
+        assert_eq!(records[2].after.as_deref(), Some("^1.0.100 (optional)"));
+    }
+}
@@ -6,10 +6,45 @@ use thiserror::Error;
 #[non_exhaustive]
 #[derive(Error, Debug)]
 pub enum Error {
-    #[error("The crate was not found on crates.io")]
+    /// The crate is not on the index queried, whichever registry it is for
+    #[error("The crate was not found on the index")]
     CrateNotFoundOnIndex,
+    /// The crate has not been cached and cannot be fetched in offline mode
+    #[error("The crate {0} has not been cached and cannot be fetched in offline mode")]
+    NotCached(String),
+    /// The crate has no published version with the version number
+    #[error("Version {1} of {0} was not found on the index")]
+    VersionNotFound(String, String),
+    /// No published version of the crate is selected by the filters
+    #[error("No version of {0} matches: {1}")]
+    NoMatchingVersion(String, String),
+    /// A crate declares a Rust version that is not `major.minor` or
+    /// `major.minor.patch`
+    #[error("Invalid Rust version {0}, expected major.minor or major.minor.patch")]
+    InvalidRustVersion(String),
+    /// The local registry path does not contain a registry index
+    #[error("No local registry index was found at {0}")]
+    LocalRegistryNotFound(String),
     #[error("Local registry builder has not been set. Please initialis_local_registry() first.")]
     LocalRegistryBuilderNotSet,
+    /// The registry requires authentication but no token was found for it
+    #[error("The registry requires authentication but no token was found")]
+    AuthenticationRequired,
+    /// The cargo credentials file could not be parsed
+    #[error("Unable to parse the credentials file: {0}")]
+    InvalidCredentials(String),
+    /// The lockfile could not be parsed
+    #[error("Unable to parse the lockfile {0}: {1}")]
+    InvalidLockfile(String, String),
+    /// The manifest could not be parsed
+    #[error("Unable to parse the manifest {0}: {1}")]
+    InvalidManifest(String, String),
+    /// A registry package in the lockfile has no checksum to verify it with
+    #[error("The lockfile has no checksum for {0} {1}")]
+    MissingChecksum(String, String),
+    /// The checksum in the lockfile differs from the one on the index
+    #[error("The checksum of {0} {1} in the lockfile does not match the index")]
+    ChecksumMismatch(String, String),
     #[error("tame_index error says: {0:?}")]
     TameIndex(#[from] tame_index::Error),
     /// Error passed up from reqwest
@@ -21,6 +56,18 @@ pub enum Error {
     /// Error passed up from semver
     #[error("Semver error says: {0:?}")]
     Semver(#[from] semver::Error),
+    /// Error passed up from serde_json
+    #[error("json error says: {0:?}")]
+    Json(#[from] serde_json::Error),
+    /// Error passed up from serde_norway
+    #[error("yaml error says: {0:?}")]
+    Yaml(#[from] serde_norway::Error),
+    /// Error passed up from toml
+    #[error("toml error says: {0:?}")]
+    Toml(#[from] toml::ser::Error),
+    /// Error passed up from csv
+    #[error("csv error says: {0:?}")]
+    Csv(#[from] csv::Error),
     /// Error passed up from io
     #[error("io error says: {0:?}")]
     Io(#[from] std::io::Error),
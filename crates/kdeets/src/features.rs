@@ -0,0 +1,418 @@
+//! Structured summary of the features of a crate version
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+use tame_index::IndexVersion;
+
+use crate::Report;
+
+/// The features of a version of a crate and the optional dependencies they
+/// enable
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeatureSummary {
+    /// The name of the crate
+    pub name: String,
+    /// The version of the crate
+    pub version: String,
+    /// Every feature, `default` first and the rest by name
+    pub features: Vec<Feature>,
+    /// Every optional dependency
+    pub optional_dependencies: Vec<OptionalDependency>,
+}
+
+/// A feature of a crate version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Feature {
+    /// The name of the feature
+    pub name: String,
+    /// The features and dependencies the feature enables, as written in the
+    /// manifest: `feature`, `dep:dependency`, `dependency/feature` or
+    /// `dependency?/feature`
+    pub enables: Vec<String>,
+    /// Whether the feature is enabled by default, directly or through another
+    /// default feature
+    pub default: bool,
+    /// Whether the feature is created implicitly for an optional dependency
+    pub implicit: bool,
+}
+
+/// An optional dependency of a crate version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OptionalDependency {
+    /// The name of the dependency, as used in feature names
+    pub name: String,
+    /// The features that enable the dependency directly
+    pub enabled_by: Vec<String>,
+    /// Whether the dependency has an implicit feature of the same name. When
+    /// `false` the dependency is only reachable through `enabled_by`
+    pub implicit_feature: bool,
+}
+
+/// What enabling some features of a crate version turns on
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct EnabledFeatures {
+    /// The dependencies enabled, by the name used in feature names
+    pub(crate) dependencies: BTreeSet<String>,
+    /// The features enabled on each dependency, including through weak
+    /// `dependency?/feature` entries
+    pub(crate) dependency_features: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl FeatureSummary {
+    /// Returns the optional dependencies that can only be enabled through
+    /// other features
+    pub fn feature_only_dependencies(&self) -> impl Iterator<Item = &OptionalDependency> {
+        self.optional_dependencies
+            .iter()
+            .filter(|dependency| !dependency.implicit_feature)
+    }
+
+    /// Follows the features requested, and the `default` feature if
+    /// `default` is set, to the dependencies and dependency features they
+    /// enable. Unknown features are ignored.
+    pub(crate) fn enable<'f>(
+        &self,
+        requested: impl IntoIterator<Item = &'f String>,
+        default: bool,
+    ) -> EnabledFeatures {
+        let mut pending = requested.into_iter().cloned().collect::<Vec<_>>();
+        if default {
+            pending.push("default".to_string());
+        }
+
+        let mut enabled = BTreeSet::new();
+        let mut result = EnabledFeatures::default();
+        while let Some(name) = pending.pop() {
+            let Some(feature) = self.features.iter().find(|feature| feature.name == name) else {
+                continue;
+            };
+            if !enabled.insert(name) {
+                continue;
+            }
+            for enable in &feature.enables {
+                if let Some(dependency) = enabled_dependency(enable) {
+                    result.dependencies.insert(dependency.to_string());
+                }
+                match enable.split_once('/') {
+                    Some((dependency, dependency_feature)) => {
+                        result
+                            .dependency_features
+                            .entry(dependency.trim_end_matches('?').to_string())
+                            .or_default()
+                            .insert(dependency_feature.to_string());
+                    }
+                    None if !enable.contains(':') => pending.push(enable.clone()),
+                    None => {}
+                }
+            }
+        }
+
+        result
+    }
+}
+
+impl From<&IndexVersion> for FeatureSummary {
+    fn from(version: &IndexVersion) -> Self {
+        let explicit = version
+            .features()
+            .map(|(name, enables)| (name.clone(), enables.clone()))
+            .collect::<Vec<_>>();
+
+        let optional = version
+            .dependencies()
+            .iter()
+            .filter(|dependency| dependency.is_optional())
+            .map(|dependency| dependency.name.to_string())
+            .collect::<BTreeSet<_>>();
+
+        // An optional dependency named with `dep:` anywhere has no implicit feature
+        let namespaced = explicit
+            .iter()
+            .flat_map(|(_, enables)| enables)
+            .filter_map(|enable| enable.strip_prefix("dep:"))
+            .collect::<BTreeSet<_>>();
+
+        let mut features = explicit
+            .iter()
+            .map(|(name, enables)| Feature {
+                name: name.clone(),
+                enables: enables.clone(),
+                default: false,
+                implicit: false,
+            })
+            .collect::<Vec<_>>();
+        features.extend(
+            optional
+                .iter()
+                .filter(|name| !namespaced.contains(name.as_str()))
+                .filter(|name| !explicit.iter().any(|(feature, _)| feature == *name))
+                .map(|name| Feature {
+                    name: name.clone(),
+                    enables: vec![format!("dep:{name}")],
+                    default: false,
+                    implicit: true,
+                }),
+        );
+        features
+            .sort_by(|a, b| (a.name != "default", &a.name).cmp(&(b.name != "default", &b.name)));
+        mark_default_features(&mut features);
+
+        let optional_dependencies = optional
+            .iter()
+            .map(|name| OptionalDependency {
+                name: name.clone(),
+                enabled_by: features
+                    .iter()
+                    .filter(|feature| {
+                        feature
+                            .enables
+                            .iter()
+                            .any(|enable| enabled_dependency(enable) == Some(name.as_str()))
+                    })
+                    .map(|feature| feature.name.clone())
+                    .collect(),
+                implicit_feature: !namespaced.contains(name.as_str()),
+            })
+            .collect();
+
+        Self {
+            name: version.name.to_string(),
+            version: version.version.to_string(),
+            features,
+            optional_dependencies,
+        }
+    }
+}
+
+/// Returns the dependency an entry of a feature enables, if any. Weak
+/// `dependency?/feature` entries do not enable the dependency.
+fn enabled_dependency(enable: &str) -> Option<&str> {
+    if let Some(dependency) = enable.strip_prefix("dep:") {
+        return Some(dependency);
+    }
+    match enable.split_once('/') {
+        Some((dependency, _)) if !dependency.ends_with('?') => Some(dependency),
+        _ => None,
+    }
+}
+
+/// Marks the `default` feature and every feature it enables, transitively
+fn mark_default_features(features: &mut [Feature]) {
+    let mut pending = vec!["default".to_string()];
+    while let Some(name) = pending.pop() {
+        let Some(feature) = features.iter_mut().find(|feature| feature.name == name) else {
+            continue;
+        };
+        if feature.default {
+            continue;
+        }
+        feature.default = true;
+        pending.extend(
+            feature
+                .enables
+                .iter()
+                .filter(|enable| !enable.contains(':') && !enable.contains('/'))
+                .cloned(),
+        );
+    }
+}
+
+/// A row of the CSV output: a feature of the crate version
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub(crate) struct FeatureRecord {
+    #[serde(rename = "crate")]
+    name: String,
+    version: String,
+    feature: String,
+    default: bool,
+    implicit: bool,
+    enables: String,
+}
+
+impl Report for FeatureSummary {
+    type Record = FeatureRecord;
+
+    const CSV_HEADERS: &'static [&'static str] = &[
+        "crate", "version", "feature", "default", "implicit", "enables",
+    ];
+
+    fn records(&self) -> Vec<Self::Record> {
+        self.features
+            .iter()
+            .map(|feature| FeatureRecord {
+                name: self.name.clone(),
+                version: self.version.clone(),
+                feature: feature.name.clone(),
+                default: feature.default,
+                implicit: feature.implicit,
+                enables: feature.enables.join(" "),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tame_index::IndexKrate;
+
+    use super::*;
+
+    fn features_of(path: &str) -> FeatureSummary {
+        let index_crate =
+            IndexKrate::new(format!("{}/index/{path}", crate::tests::TEST_REGISTRY)).unwrap();
+        FeatureSummary::from(index_crate.most_recent_version())
+    }
+
+    fn feature(name: &str, enables: &[&str], default: bool, implicit: bool) -> Feature {
+        Feature {
+            name: name.to_string(),
+            enables: enables.iter().map(ToString::to_string).collect(),
+            default,
+            implicit,
+        }
+    }
+
+    #[test]
+    fn test_features_without_optional_dependencies() {
+        let summary = features_of("fo/re/forestry");
+
+        assert_eq!(summary.name, "forestry");
+        assert_eq!(summary.version, "1.4.1");
+        assert_eq!(
+            summary.features,
+            vec![feature("plain", &["colored/no-color"], false, false)]
+        );
+        assert!(summary.optional_dependencies.is_empty());
+    }
+
+    #[test]
+    fn test_default_features_are_transitive() {
+        let summary = features_of("pr/oc/proc-macro2");
+
+        let defaults = summary
+            .features
+            .iter()
+            .filter(|feature| feature.default)
+            .map(|feature| feature.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(summary.features[0].name, "default");
+        assert_eq!(defaults, vec!["default", "proc-macro"]);
+    }
+
+    #[test]
+    fn test_namespaced_optional_dependency() {
+        let summary = features_of("la/zy/lazy_static");
+
+        assert_eq!(
+            summary.features,
+            vec![
+                feature("spin", &["dep:spin"], false, false),
+                feature("spin_no_std", &["spin"], false, false),
+            ]
+        );
+        assert_eq!(
+            summary.optional_dependencies,
+            vec![OptionalDependency {
+                name: "spin".to_string(),
+                enabled_by: vec!["spin".to_string()],
+                implicit_feature: false,
+            }]
+        );
+        assert_eq!(summary.feature_only_dependencies().count(), 1);
+    }
+
+    #[test]
+    fn test_dependency_enabled_through_dependency_feature() {
+        let summary = features_of("3/s/syn");
+
+        assert_eq!(
+            summary.optional_dependencies,
+            vec![OptionalDependency {
+                name: "quote".to_string(),
+                enabled_by: vec!["proc-macro".to_string(), "quote".to_string()],
+                implicit_feature: false,
+            }]
+        );
+        let printing = summary
+            .features
+            .iter()
+            .find(|feature| feature.name == "printing")
+            .unwrap();
+        assert!(printing.default);
+    }
+
+    #[test]
+    fn test_implicit_feature() {
+        let mut index_crate = IndexKrate::new(format!(
+            "{}/index/la/zy/lazy_static",
+            crate::tests::TEST_REGISTRY
+        ))
+        .unwrap();
+        let version = index_crate.versions.pop().unwrap();
+        let json = serde_json::to_string(&version)
+            .unwrap()
+            .replace(r#""spin":["dep:spin"],"#, "");
+        let version: IndexVersion = serde_json::from_str(&json).unwrap();
+
+        let summary = FeatureSummary::from(&version);
+
+        assert_eq!(
+            summary.features,
+            vec![
+                feature("spin", &["dep:spin"], false, true),
+                feature("spin_no_std", &["spin"], false, false),
+            ]
+        );
+        assert!(summary.optional_dependencies[0].implicit_feature);
+        assert_eq!(summary.feature_only_dependencies().count(), 0);
+    }
+
+    #[test]
+    fn test_enabled_dependency() {
+        assert_eq!(enabled_dependency("dep:serde"), Some("serde"));
+        assert_eq!(enabled_dependency("serde/std"), Some("serde"));
+        assert_eq!(enabled_dependency("serde?/std"), None);
+        assert_eq!(enabled_dependency("std"), None);
+    }
+
+    #[test]
+    fn test_enable() {
+        let summary = features_of("la/zy/lazy_static");
+
+        assert_eq!(summary.enable(&[], true), EnabledFeatures::default());
+        let enabled = summary.enable(&["spin_no_std".to_string()], true);
+        assert_eq!(enabled.dependencies, BTreeSet::from(["spin".to_string()]));
+
+        let summary = features_of("fo/re/forestry");
+        let enabled = summary.enable(&["plain".to_string(), "unknown".to_string()], false);
+        assert_eq!(
+            enabled.dependencies,
+            BTreeSet::from(["colored".to_string()])
+        );
+        assert_eq!(
+            enabled.dependency_features,
+            BTreeMap::from([(
+                "colored".to_string(),
+                BTreeSet::from(["no-color".to_string()])
+            )])
+        );
+    }
+
+    #[test]
+    fn test_records() {
+        let summary = features_of("fo/re/forestry");
+
+        assert_eq!(
+            summary.records(),
+            vec![FeatureRecord {
+                name: "forestry".to_string(),
+                version: "1.4.1".to_string(),
+                feature: "plain".to_string(),
+                default: false,
+                implicit: false,
+                enables: "colored/no-color".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,170 @@
+//! Machine-readable output formats
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::Error;
+
+/// The format in which a command reports its results: human readable text,
+/// a JSON, YAML or TOML document, or comma separated values with a header row
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Yaml,
+    Toml,
+    Csv,
+}
+
+/// The data reported by a command, serialisable as a document or as rows
+pub(crate) trait Report: Serialize {
+    /// A row of the CSV output
+    type Record: Serialize;
+
+    /// The header row of the CSV output, naming the fields of [`Self::Record`]
+    const CSV_HEADERS: &'static [&'static str];
+
+    /// The rows of the CSV output
+    fn records(&self) -> Vec<Self::Record>;
+}
+
+impl OutputFormat {
+    /// Renders the report in this format, calling `text` for the human
+    /// readable output
+    pub(crate) fn render<R: Report>(
+        self,
+        report: &R,
+        text: impl FnOnce() -> Result<String, Error>,
+    ) -> Result<String, Error> {
+        let output = match self {
+            Self::Text => text()?,
+            Self::Json => serde_json::to_string_pretty(report)?,
+            Self::Yaml => serde_norway::to_string(report)?,
+            Self::Toml => toml::to_string(report)?,
+            Self::Csv => csv_output(report)?,
+        };
+
+        // `println!` adds the final newline
+        Ok(match self {
+            Self::Text => output,
+            _ => output.trim_end().to_string(),
+        })
+    }
+}
+
+fn csv_output<R: Report>(report: &R) -> Result<String, Error> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(vec![]);
+
+    writer.write_record(R::CSV_HEADERS)?;
+    for record in report.records() {
+        writer.serialize(record)?;
+    }
+
+    let bytes = writer
+        .into_inner()
+        .map_err(|e| std::io::Error::other(e.to_string()))?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct TestReport {
+        name: String,
+        rust_version: Option<String>,
+        items: Vec<TestRecord>,
+    }
+
+    #[derive(Serialize)]
+    struct TestRecord {
+        item: String,
+        size: u64,
+    }
+
+    impl Report for TestReport {
+        type Record = TestRecord;
+
+        const CSV_HEADERS: &'static [&'static str] = &["item", "size"];
+
+        fn records(&self) -> Vec<Self::Record> {
+            self.items
+                .iter()
+                .map(|item| TestRecord {
+                    item: item.item.clone(),
+                    size: item.size,
+                })
+                .collect()
+        }
+    }
+
+    fn report() -> TestReport {
+        TestReport {
+            name: "test".to_string(),
+            rust_version: None,
+            items: vec![
+                TestRecord {
+                    item: "a".to_string(),
+                    size: 1,
+                },
+                TestRecord {
+                    item: "b,c".to_string(),
+                    size: 2,
+                },
+            ],
+        }
+    }
+
+    fn render(format: OutputFormat, report: &TestReport) -> String {
+        format.render(report, || Ok("text".to_string())).unwrap()
+    }
+
+    #[test]
+    fn test_render_text() {
+        assert_eq!(render(OutputFormat::Text, &report()), "text");
+    }
+
+    #[test]
+    fn test_render_json() {
+        let output = render(OutputFormat::Json, &report());
+        let value: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(value["name"], "test");
+        assert!(value["rust_version"].is_null());
+        assert_eq!(value["items"][1]["item"], "b,c");
+    }
+
+    #[test]
+    fn test_render_yaml() {
+        let expected =
+            "name: test\nrust_version: null\nitems:\n- item: a\n  size: 1\n- item: b,c\n  size: 2";
+        assert_eq!(render(OutputFormat::Yaml, &report()), expected);
+    }
+
+    #[test]
+    fn test_render_toml() {
+        let output = render(OutputFormat::Toml, &report());
+        let value: toml::Table = toml::from_str(&output).unwrap();
+        assert_eq!(value["name"].as_str(), Some("test"));
+        assert!(!value.contains_key("rust_version"));
+        assert_eq!(value["items"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_render_csv() {
+        let expected = "item,size\na,1\n\"b,c\",2";
+        assert_eq!(render(OutputFormat::Csv, &report()), expected);
+    }
+
+    #[test]
+    fn test_render_csv_without_records() {
+        let empty = TestReport {
+            items: vec![],
+            ..report()
+        };
+        assert_eq!(render(OutputFormat::Csv, &empty), "item,size");
+    }
+}
@@ -1,23 +1,49 @@
 const HEADER: &str = "Crate versions for";
+const DEPENDENCIES_HEADER: &str = "Dependencies of";
+const FEATURES_HEADER: &str = "Features of";
+const DIFF_HEADER: &str = "Changes to";
 const SETUP_HEADER: &str = "Local registry set up for";
 const LINE_CHAR: char = '🭶';
 
+mod client;
 mod combo;
+mod crate_diff;
 mod crate_versions;
+mod dependencies;
+mod diff;
 mod error;
+mod features;
+mod format;
+mod registry;
+mod rust_history;
+mod rust_version;
 mod rust_versions;
 mod setup;
+mod summary;
+mod walk;
 
+pub use client::Kdeets;
+pub use crate_diff::CrateDiff;
 pub use crate_versions::CrateVersions;
+pub use dependencies::{Dependency, DependencyKind, DependencySummary};
+pub use diff::{ChangeKind, DependencyChange, FeatureChange, RustVersionChange, VersionDiff};
 pub use error::Error;
+pub use features::{Feature, FeatureSummary, OptionalDependency};
+pub use format::OutputFormat;
+pub use registry::{IndexOptions, Registry};
+pub use rust_version::RustVersion;
 pub use rust_versions::RustVersions;
-pub use setup::Setup;
+pub use setup::{RegistryCommand, Setup};
+pub use summary::{CrateSummary, KeyVersions, VersionSummary};
 
-pub(crate) use combo::ComboIndex;
+pub(crate) use combo::{ComboIndex, GitCliIndex, HttpCache};
+pub(crate) use format::Report;
+pub(crate) use registry::Token;
 
-use reqwest::blocking::ClientBuilder;
-use tame_index::index::RemoteSparseIndex;
-use tame_index::{IndexLocation, IndexUrl, SparseIndex};
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, ClientBuilder};
+use tame_index::index::{IndexConfig, RemoteSparseIndex};
+use tame_index::{IndexLocation, IndexPath, IndexUrl, Path, PathBuf, SparseIndex};
 
 /// Returns `true` if the given version of a crate exists in the crates.io index,
 /// or `false` if the crate is found but the version is absent.
@@ -25,6 +51,9 @@ use tame_index::{IndexLocation, IndexUrl, SparseIndex};
 /// Returns an error if the crate is not found on the index or if the index
 /// cannot be queried.
 ///
+/// Each call opens the index afresh; use [`Kdeets`] to make several queries
+/// against the same index.
+///
 /// # Errors
 ///
 /// Returns [`Error::CrateNotFoundOnIndex`] when the crate is absent from the index.
@@ -42,8 +71,7 @@ use tame_index::{IndexLocation, IndexUrl, SparseIndex};
 /// # }
 /// ```
 pub fn version_exists(crate_name: &str, version: &str) -> Result<bool, Error> {
-    let index = get_remote_combo_index()?;
-    version_exists_in_index(&index, crate_name, version)
+    Kdeets::crates_io()?.version_exists(crate_name, version)
 }
 
 /// Returns all published version strings for a crate from the crates.io index.
@@ -65,8 +93,30 @@ pub fn version_exists(crate_name: &str, version: &str) -> Result<bool, Error> {
 /// # }
 /// ```
 pub fn list_versions(crate_name: &str) -> Result<Vec<String>, Error> {
-    let index = get_remote_combo_index()?;
-    list_versions_in_index(&index, crate_name)
+    Kdeets::crates_io()?.list_versions(crate_name)
+}
+
+/// Returns the key versions and every published version of a crate from the
+/// crates.io index.
+///
+/// # Errors
+///
+/// Returns [`Error::CrateNotFoundOnIndex`] when the crate is absent from the index.
+/// Returns other [`Error`] variants on index access failures.
+///
+/// # Examples
+///
+/// ```no_run
+/// use kdeets_lib::crate_summary;
+///
+/// # fn main() -> Result<(), kdeets_lib::Error> {
+/// let summary = crate_summary("serde")?;
+/// println!("{} {}", summary.name, summary.key_versions.highest);
+/// # Ok(())
+/// # }
+/// ```
+pub fn crate_summary(crate_name: &str) -> Result<CrateSummary, Error> {
+    Kdeets::crates_io()?.summary(crate_name)
 }
 
 pub(crate) fn version_exists_in_index(
@@ -77,10 +127,10 @@ pub(crate) fn version_exists_in_index(
     use tame_index::{KrateName, index::FileLock};
 
     let lock = FileLock::unlocked();
-    let index_krate = index.krate(KrateName::crates_io(crate_name)?, true, &lock)?;
+    let index_krate = index.krate(KrateName::cargo(crate_name)?, true, &lock)?;
 
     let Some(index_krate) = index_krate else {
-        return Err(Error::CrateNotFoundOnIndex);
+        return Err(index.not_found(crate_name));
     };
 
     Ok(index_krate
@@ -96,10 +146,10 @@ pub(crate) fn list_versions_in_index(
     use tame_index::{KrateName, index::FileLock};
 
     let lock = FileLock::unlocked();
-    let index_krate = index.krate(KrateName::crates_io(crate_name)?, true, &lock)?;
+    let index_krate = index.krate(KrateName::cargo(crate_name)?, true, &lock)?;
 
     let Some(index_krate) = index_krate else {
-        return Err(Error::CrateNotFoundOnIndex);
+        return Err(index.not_found(crate_name));
     };
 
     Ok(index_krate
@@ -109,19 +159,143 @@ pub(crate) fn list_versions_in_index(
         .collect())
 }
 
-pub(crate) fn get_remote_combo_index() -> Result<ComboIndex, tame_index::error::Error> {
-    let index = get_sparse_index()?;
-    let builder = get_client_builder();
+/// Opens the index at the location, authenticating requests with the token
+/// when the index requires it. A sparse index answers lookups through the
+/// HTTP cache, when one is given.
+pub(crate) fn get_remote_combo_index(
+    location: IndexLocation<'_>,
+    token: Option<&Token>,
+    http_cache: Option<HttpCache>,
+) -> Result<ComboIndex, Error> {
+    if let IndexUrl::Local(path) = &location.url {
+        return Ok(ComboIndex::local(path.to_path_buf())?);
+    }
+
+    if !location.url.is_sparse() {
+        let mut git_index = GitCliIndex::new(location)?;
+        git_index.fetch()?;
+        return Ok(ComboIndex::from(git_index));
+    }
+
+    let crates_io = matches!(location.url, IndexUrl::CratesIoSparse);
+    let cache_path = index_path(&location)?;
+    let index = get_sparse_index(location)?;
+    let auth_required = !crates_io
+        && match http_cache
+            .as_ref()
+            .and_then(|cache| cache.auth_required(&index))
+        {
+            Some(auth_required) => {
+                log::debug!("Using the cached authentication requirement of the index");
+                auth_required
+            }
+            None => {
+                let auth_required =
+                    index_requires_auth(&index, &cache_path, &get_client_builder().build()?);
+                if let (Some(cache), Some(auth_required)) = (&http_cache, auth_required)
+                    && let Err(e) = cache.set_auth_required(&index, auth_required)
+                {
+                    log::debug!("Unable to cache the authentication requirement: {e}");
+                }
+                auth_required.unwrap_or_default()
+            }
+        };
+    let mut builder = get_client_builder();
+    if auth_required {
+        let Some(token) = token else {
+            return Err(Error::AuthenticationRequired);
+        };
+        log::debug!("Authenticating requests to {}", index.url());
+        builder = builder.default_headers(token.headers()?);
+    }
     let client = builder.build()?;
 
-    let remote_index = RemoteSparseIndex::new(index, client);
+    let remote_index = ComboIndex::from(RemoteSparseIndex::new(index, client));
+
+    Ok(match http_cache {
+        Some(cache) => remote_index.with_http_cache(cache),
+        None => remote_index,
+    })
+}
+
+/// Opens the index at the location without any network access, answering
+/// lookups only from the entries already cached on disk.
+pub(crate) fn get_cached_combo_index(location: IndexLocation<'_>) -> Result<ComboIndex, Error> {
+    log::debug!(
+        "Offline: using cached entries for {}",
+        location.url.as_str()
+    );
+    let index = if let IndexUrl::Local(path) = &location.url {
+        ComboIndex::local(path.to_path_buf())?
+    } else if !location.url.is_sparse() {
+        ComboIndex::from(GitCliIndex::new(location)?)
+    } else {
+        let client = get_client_builder().build()?;
+        ComboIndex::from(RemoteSparseIndex::new(get_sparse_index(location)?, client))
+    };
+
+    Ok(ComboIndex::Cached(Box::new(index)))
+}
 
-    Ok(ComboIndex::from(remote_index))
+pub(crate) fn get_sparse_index(
+    location: IndexLocation<'_>,
+) -> Result<SparseIndex, tame_index::error::Error> {
+    SparseIndex::new(location)
+}
+
+/// The directory the index at the location caches its entries in
+fn index_path(location: &IndexLocation<'_>) -> Result<PathBuf, tame_index::error::Error> {
+    let root = match &location.root {
+        IndexPath::CargoHome => IndexPath::CargoHome,
+        IndexPath::UserSpecified(root) => IndexPath::UserSpecified(root.clone()),
+        IndexPath::Exact(path) => IndexPath::Exact(path.clone()),
+    };
+    let (path, _) = IndexLocation {
+        url: IndexUrl::NonCratesIo(location.url.as_str().into()),
+        root,
+        cargo_version: location.cargo_version.clone(),
+    }
+    .into_parts()?;
+    Ok(path)
 }
 
-pub(crate) fn get_sparse_index() -> Result<SparseIndex, tame_index::error::Error> {
-    let il = IndexLocation::new(IndexUrl::CratesIoSparse);
-    SparseIndex::new(il)
+/// Determines from its `config.json` whether the index requires every request
+/// to be authenticated.
+///
+/// The configuration is fetched once and saved to the index cache, where
+/// [`ComboIndex::index_config`] reads it from. A registry that refuses to serve
+/// its configuration without a token is taken to require authentication, and
+/// one whose configuration cannot be fetched or parsed is unknown, and
+/// accessed unauthenticated.
+fn index_requires_auth(index: &SparseIndex, cache_path: &Path, client: &Client) -> Option<bool> {
+    let url = format!("{}config.json", index.url());
+    log::debug!("Requesting index configuration from {url}");
+    let body = match client.get(url).send() {
+        Ok(res) if res.status() == StatusCode::UNAUTHORIZED => return Some(true),
+        Ok(res) => res.error_for_status().and_then(|res| res.bytes()),
+        Err(e) => Err(e),
+    };
+    let body = match body {
+        Ok(body) => body,
+        Err(e) => {
+            log::debug!("Unable to fetch the index configuration, continuing unauthenticated: {e}");
+            return None;
+        }
+    };
+    let config: IndexConfig = match serde_json::from_slice(&body) {
+        Ok(config) => config,
+        Err(e) => {
+            log::debug!("Unable to parse the index configuration, continuing unauthenticated: {e}");
+            return None;
+        }
+    };
+
+    if let Err(e) = std::fs::create_dir_all(cache_path)
+        .and_then(|()| std::fs::write(cache_path.join("config.json"), &body))
+    {
+        log::debug!("Unable to cache the index configuration: {e}");
+    }
+    Some(config.auth_required)
 }
 
 pub(crate) fn get_client_builder() -> ClientBuilder {
@@ -145,14 +319,17 @@ pub(crate) fn get_client_builder() -> ClientBuilder {
 #[cfg(test)]
 mod tests {
 
-    use std::vec;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{SocketAddr, TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+    use std::{fs, vec};
 
-    use crate::ComboIndex;
-    use crate::get_remote_combo_index;
-    use tame_index::{PathBuf, index::LocalRegistry};
+    use crate::{ComboIndex, HttpCache, Registry, Token};
+    use crate::{get_cached_combo_index, get_remote_combo_index};
+    use tame_index::{IndexLocation, IndexUrl, PathBuf};
     use tempfile::TempDir;
 
-    const TEST_REGISTRY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/registry");
+    pub(crate) const TEST_REGISTRY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/registry");
 
     pub(crate) fn get_temp_local_registry() -> (TempDir, String) {
         let temp_dir = tempfile::tempdir().unwrap();
@@ -171,14 +348,224 @@ mod tests {
     }
 
     pub(crate) fn get_test_index(registry: &str) -> Result<ComboIndex, tame_index::error::Error> {
-        let local_registry = LocalRegistry::open(PathBuf::from(registry), false)?;
+        ComboIndex::local(PathBuf::from(registry))
+    }
 
-        Ok(ComboIndex::from(local_registry))
+    /// The paths requested from a loopback test registry and the status of
+    /// each response
+    pub(crate) type RequestLog = Arc<Mutex<Vec<(String, u16)>>>;
+
+    /// Serves the test registry as a sparse index over loopback HTTP and
+    /// returns the index url.
+    ///
+    /// When a token is given the index configuration sets `auth-required` and
+    /// any request that does not carry the token is refused.
+    pub(crate) fn serve_test_registry(token: Option<&'static str>) -> String {
+        serve_logged_test_registry(token).0
+    }
+
+    /// Serves the test registry as [`serve_test_registry`] does, also
+    /// returning the log of requests it answers.
+    ///
+    /// Index files carry an `ETag` and conditional requests for an unchanged
+    /// file are answered with `304 Not Modified`.
+    pub(crate) fn serve_logged_test_registry(token: Option<&'static str>) -> (String, RequestLog) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let log = RequestLog::default();
+
+        let server_log = Arc::clone(&log);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                respond_from_test_registry(stream, addr, token, &server_log);
+            }
+        });
+
+        (format!("sparse+http://{addr}/"), log)
+    }
+
+    fn respond_from_test_registry(
+        mut stream: TcpStream,
+        addr: SocketAddr,
+        token: Option<&'static str>,
+        log: &RequestLog,
+    ) {
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request_line = String::new();
+        let _ = reader.read_line(&mut request_line);
+        let mut authorization = None;
+        let mut if_none_match = None;
+        loop {
+            let mut header = String::new();
+            if reader.read_line(&mut header).unwrap_or(0) <= 2 {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("authorization") {
+                    authorization = Some(value.trim().to_string());
+                } else if name.eq_ignore_ascii_case("if-none-match") {
+                    if_none_match = Some(value.trim().to_string());
+                }
+            }
+        }
+
+        let path = request_line
+            .split_whitespace()
+            .nth(1)
+            .unwrap_or("/")
+            .trim_start_matches('/')
+            .to_string();
+
+        let authorised = token.is_none_or(|token| authorization.as_deref() == Some(token));
+
+        let mut etag = None;
+        let body = if !authorised {
+            None
+        } else if path == "config.json" {
+            Some(
+                format!(
+                    r#"{{"dl":"http://{addr}/{{crate}}-{{version}}.crate","auth-required":{}}}"#,
+                    token.is_some()
+                )
+                .into_bytes(),
+            )
+        } else if path.ends_with(".crate") {
+            fs::read(format!("{TEST_REGISTRY}/{path}")).ok()
+        } else {
+            let body = fs::read(format!("{TEST_REGISTRY}/index/{path}")).ok();
+            etag = body.as_ref().map(|body| format!("\"{}\"", body.len()));
+            body
+        };
+
+        let status = match (&body, &etag) {
+            _ if !authorised => 401,
+            (None, _) => 404,
+            (Some(_), Some(etag)) if if_none_match.as_ref() == Some(etag) => 304,
+            (Some(_), _) => 200,
+        };
+        log.lock().unwrap().push((path, status));
+
+        let _ = match (status, body) {
+            (200, Some(body)) => {
+                let etag = etag
+                    .map(|etag| format!("ETag: {etag}\r\n"))
+                    .unwrap_or_default();
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\n{etag}Content-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                stream.write_all(&body)
+            }
+            (304, _) => stream.write_all(b"HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n"),
+            (401, _) => stream.write_all(
+                b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            ),
+            _ => stream.write_all(
+                b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            ),
+        };
+    }
+
+    pub(crate) fn test_sparse_location(url: String) -> (TempDir, IndexLocation<'static>) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_root = PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let location =
+            IndexLocation::new(IndexUrl::NonCratesIo(url.into())).with_root(Some(cache_root));
+
+        (temp_dir, location)
+    }
+
+    /// Creates a sparse index for the loopback test registry, caching index
+    /// entries in a temporary directory.
+    pub(crate) fn get_test_sparse_index() -> (TempDir, ComboIndex) {
+        let (temp_dir, location) = test_sparse_location(serve_test_registry(None));
+        let index = get_remote_combo_index(location, None, None).unwrap();
+
+        (temp_dir, index)
+    }
+
+    /// Creates a sparse index for the loopback test registry requiring the
+    /// token `secret-token`, authenticated with `token`.
+    pub(crate) fn get_authenticated_test_sparse_index(
+        token: Option<&str>,
+    ) -> (TempDir, Result<ComboIndex, crate::Error>) {
+        let (temp_dir, location) = test_sparse_location(serve_test_registry(Some("secret-token")));
+        let token = token.map(Token::new);
+        let index = get_remote_combo_index(location, token.as_ref(), None);
+
+        (temp_dir, index)
+    }
+
+    fn git(dir: &std::path::Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .args([
+                "-c",
+                "user.name=kdeets",
+                "-c",
+                "user.email=kdeets@example.com",
+            ])
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {args:?} failed");
+    }
+
+    /// Creates a bare git repository holding the test registry index, with a
+    /// `config.json` pointing downloads at the loopback test registry, and
+    /// opens it as a git index.
+    pub(crate) fn get_test_git_index() -> (TempDir, ComboIndex) {
+        get_test_git_index_with_format("sha1")
+    }
+
+    /// Creates the git index as [`get_test_git_index`] does, in a repository
+    /// using the object format given
+    fn get_test_git_index_with_format(object_format: &str) -> (TempDir, ComboIndex) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let work = temp_dir.path().join("work");
+        let bare = temp_dir.path().join("index.git");
+
+        let _ = fs_extra::dir::copy(
+            format!("{TEST_REGISTRY}/index"),
+            &work,
+            &fs_extra::dir::CopyOptions::new().content_only(true),
+        )
+        .unwrap();
+        let dl = serve_test_registry(None).replace("sparse+", "");
+        fs::write(
+            work.join("config.json"),
+            format!(r#"{{"dl":"{dl}{{crate}}-{{version}}.crate"}}"#),
+        )
+        .unwrap();
+        git(
+            &work,
+            &[
+                "init",
+                "--quiet",
+                &format!("--object-format={object_format}"),
+            ],
+        );
+        git(&work, &["add", "."]);
+        git(&work, &["commit", "--quiet", "-m", "Test registry index"]);
+        git(
+            temp_dir.path(),
+            &["clone", "--bare", "--quiet", "work", "index.git"],
+        );
+
+        let cache_root = PathBuf::from_path_buf(temp_dir.path().join("cache")).unwrap();
+        let url = format!("file://{}", bare.display());
+        let location =
+            IndexLocation::new(IndexUrl::NonCratesIo(url.into())).with_root(Some(cache_root));
+        let index = get_remote_combo_index(location, None, None).unwrap();
+
+        (temp_dir, index)
     }
 
     #[test]
     fn test_get_sparse_index_success() {
-        let result = get_remote_combo_index();
+        let result =
+            get_remote_combo_index(Registry::CratesIo.index_location().unwrap(), None, None);
         assert!(result.is_ok());
         let index = result.unwrap();
         assert!(matches!(index, ComboIndex::Sparse(_)));
@@ -186,19 +573,245 @@ mod tests {
 
     #[test]
     fn test_get_sparse_index_type() {
-        let result = get_remote_combo_index();
+        let result =
+            get_remote_combo_index(Registry::CratesIo.index_location().unwrap(), None, None);
         assert!(matches!(result, Ok(ComboIndex::Sparse(_))));
     }
 
     #[test]
     fn test_sparse_index_error_handling() {
-        let result = get_remote_combo_index();
+        let result =
+            get_remote_combo_index(Registry::CratesIo.index_location().unwrap(), None, None);
         match result {
             Ok(_) => (),
             Err(e) => panic!("Expected Ok, got Err: {e:?}"),
         }
     }
 
+    #[test]
+    fn test_get_combo_index_for_local_url() {
+        let (_temp_dir, registry) = get_temp_local_registry();
+        let location = IndexLocation::new(IndexUrl::Local(PathBuf::from(registry).into()));
+        let result = get_remote_combo_index(location, None, None);
+        assert!(matches!(result, Ok(ComboIndex::Local(..))));
+    }
+
+    #[test]
+    fn test_get_combo_index_for_git_url() {
+        let (_temp_dir, index) = get_test_git_index();
+        assert!(matches!(index, ComboIndex::Git(_)));
+    }
+
+    #[test]
+    fn test_get_combo_index_for_missing_git_repository_is_error() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_root = PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let url = format!("file://{}/missing.git", temp_dir.path().display());
+        let location =
+            IndexLocation::new(IndexUrl::NonCratesIo(url.into())).with_root(Some(cache_root));
+        let result = get_remote_combo_index(location, None, None);
+        assert!(result.is_err());
+    }
+
+    // Tests for a git registry index in a local bare repository
+
+    #[test]
+    fn test_git_registry_version_exists() {
+        let (_temp_dir, index) = get_test_git_index();
+        let result = crate::version_exists_in_index(&index, "some_crate", "0.2.1");
+        assert!(result.is_ok(), "Expected Ok, got {result:?}");
+        assert!(result.unwrap(), "Expected version 0.2.1 to exist");
+    }
+
+    #[test]
+    fn test_git_registry_crate_not_on_index() {
+        let (_temp_dir, index) = get_test_git_index();
+        let result = crate::list_versions_in_index(&index, "not_in_this_registry");
+        assert!(matches!(result, Err(crate::Error::CrateNotFoundOnIndex)));
+    }
+
+    #[test]
+    fn test_git_registry_writes_cache_entry() {
+        use tame_index::{KrateName, index::FileLock};
+
+        let (_temp_dir, index) = get_test_git_index();
+        let lock = FileLock::unlocked();
+        let name = KrateName::cargo("some_crate").unwrap();
+        assert!(index.cached_krate(name, &lock).unwrap().is_none());
+
+        let krate = index.krate(name, true, &lock).unwrap();
+        assert!(krate.is_some());
+        assert_eq!(index.cached_krate(name, &lock).unwrap(), krate);
+    }
+
+    #[test]
+    fn test_git_registry_with_sha256_object_ids() {
+        let (_temp_dir, index) = get_test_git_index_with_format("sha256");
+        let result = crate::version_exists_in_index(&index, "some_crate", "0.2.1");
+        assert!(result.unwrap(), "Expected version 0.2.1 to exist");
+    }
+
+    #[test]
+    fn test_git_registry_index_config() {
+        let (_temp_dir, index) = get_test_git_index();
+        let config = index.index_config().unwrap();
+        assert!(config.dl.starts_with("http://127.0.0.1:"));
+        assert!(!config.auth_required);
+    }
+
+    // Loopback tests for an alternative sparse registry
+
+    #[test]
+    fn test_alternative_registry_version_exists() {
+        let (_temp_dir, index) = get_test_sparse_index();
+        let result = crate::version_exists_in_index(&index, "some_crate", "0.2.1");
+        assert!(result.is_ok(), "Expected Ok, got {result:?}");
+        assert!(result.unwrap(), "Expected version 0.2.1 to exist");
+    }
+
+    #[test]
+    fn test_alternative_registry_crate_not_on_index() {
+        let (_temp_dir, index) = get_test_sparse_index();
+        let result = crate::list_versions_in_index(&index, "nonexistent-crate-xyz");
+        assert!(
+            matches!(result, Err(crate::Error::CrateNotFoundOnIndex)),
+            "Expected CrateNotFoundOnIndex, got {result:?}"
+        );
+    }
+
+    #[test]
+    fn test_authenticated_registry_with_token() {
+        let (_temp_dir, index) = get_authenticated_test_sparse_index(Some("secret-token"));
+        let index = index.unwrap();
+        let result = crate::version_exists_in_index(&index, "some_crate", "0.2.1");
+        assert!(result.is_ok(), "Expected Ok, got {result:?}");
+        assert!(result.unwrap(), "Expected version 0.2.1 to exist");
+        assert!(index.index_config().unwrap().auth_required);
+    }
+
+    #[test]
+    fn test_authenticated_registry_without_token() {
+        let (_temp_dir, index) = get_authenticated_test_sparse_index(None);
+        assert!(
+            matches!(index, Err(crate::Error::AuthenticationRequired)),
+            "Expected AuthenticationRequired"
+        );
+    }
+
+    #[test]
+    fn test_authenticated_registry_with_wrong_token() {
+        let (_temp_dir, index) = get_authenticated_test_sparse_index(Some("wrong-token"));
+        let index = index.unwrap();
+        let result = crate::version_exists_in_index(&index, "some_crate", "0.2.1");
+        assert!(result.is_err(), "Expected Err, got {result:?}");
+    }
+
+    // Offline lookups from cached index entries
+
+    #[test]
+    fn test_offline_answers_from_cached_entries() {
+        let url = serve_test_registry(None);
+        let (temp_dir, location) = test_sparse_location(url.clone());
+        let index = get_remote_combo_index(location, None, None).unwrap();
+        assert!(crate::version_exists_in_index(&index, "some_crate", "0.2.1").unwrap());
+
+        let cache_root = PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        let location =
+            IndexLocation::new(IndexUrl::NonCratesIo(url.into())).with_root(Some(cache_root));
+        let offline = get_cached_combo_index(location).unwrap();
+        assert!(offline.is_offline());
+
+        let result = crate::version_exists_in_index(&offline, "some_crate", "0.2.1");
+        assert!(result.is_ok(), "Expected Ok, got {result:?}");
+        assert!(result.unwrap(), "Expected version 0.2.1 to exist");
+    }
+
+    #[test]
+    fn test_offline_crate_not_cached() {
+        let (_temp_dir, location) = test_sparse_location(serve_test_registry(None));
+        let index = get_cached_combo_index(location).unwrap();
+
+        let result = crate::list_versions_in_index(&index, "some_crate");
+        assert!(
+            matches!(&result, Err(crate::Error::NotCached(name)) if name == "some_crate"),
+            "Expected NotCached, got {result:?}"
+        );
+    }
+
+    #[test]
+    fn test_offline_does_not_download() {
+        let url = serve_test_registry(None);
+        let config = tame_index::index::IndexConfig {
+            dl: format!("{}{{crate}}-{{version}}.crate", url.replace("sparse+", "")),
+            api: None,
+            auth_required: false,
+        };
+        let (_temp_dir, location) = test_sparse_location(url);
+        let index = get_cached_combo_index(location).unwrap();
+        let krate =
+            tame_index::IndexKrate::new(format!("{TEST_REGISTRY}/index/so/me/some_crate")).unwrap();
+
+        assert!(index.download(&config, &krate.versions[0]).is_err());
+    }
+
+    #[test]
+    fn test_alternative_registry_index_config() {
+        let (_temp_dir, index) = get_test_sparse_index();
+        let config = index.index_config().unwrap();
+        assert!(config.dl.starts_with("http://127.0.0.1:"));
+        assert!(config.dl.ends_with("/{crate}-{version}.crate"));
+    }
+
+    #[test]
+    fn test_index_config_is_requested_once() {
+        let (url, log) = serve_logged_test_registry(None);
+        let (_temp_dir, location) = test_sparse_location(url);
+        let index = get_remote_combo_index(location, None, None).unwrap();
+
+        let config = index.index_config().unwrap();
+
+        assert!(!config.auth_required);
+        let requests = log
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(path, _)| path == "config.json")
+            .count();
+        assert_eq!(requests, 1);
+    }
+
+    #[test]
+    fn test_fresh_auth_requirement_is_not_requested_again() {
+        let (url, log) = serve_logged_test_registry(Some("secret-token"));
+        let (temp_dir, location) = test_sparse_location(url.clone());
+        let cache_root = PathBuf::from_path_buf(temp_dir.path().join("http-cache")).unwrap();
+        let cache = HttpCache::new(cache_root, Some(std::time::Duration::from_secs(3600)));
+        let token = Token::new("secret-token");
+        get_remote_combo_index(location, Some(&token), Some(cache.clone())).unwrap();
+
+        let (_temp_dir, location) = test_sparse_location(url);
+        let result = get_remote_combo_index(location, None, Some(cache));
+
+        assert!(matches!(result, Err(crate::Error::AuthenticationRequired)));
+        let requests = log
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(path, _)| path == "config.json")
+            .count();
+        assert_eq!(requests, 1);
+    }
+
+    #[test]
+    fn test_missing_index_config_is_accessed_unauthenticated() {
+        let url = format!("{}missing/", serve_test_registry(None));
+        let (_temp_dir, location) = test_sparse_location(url);
+
+        let result = get_remote_combo_index(location, None, None);
+
+        assert!(result.is_ok(), "Expected the index to open unauthenticated");
+    }
+
     // Network tests for the public API — exercise the full call chain:
     // version_exists / list_versions → get_remote_combo_index → _in_index helper
 
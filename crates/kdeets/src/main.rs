@@ -1,8 +1,13 @@
 use std::error::Error;
+use std::time::Duration;
 
 use clap::{Parser, Subcommand};
 use env_logger::Env;
-use kdeets_lib::{CrateVersions, RustVersions, Setup};
+use kdeets_lib::{
+    CrateDiff, CrateVersions, IndexOptions, OutputFormat, Registry, RegistryCommand, RustVersions,
+    Setup,
+};
+use tame_index::PathBuf;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -12,6 +17,34 @@ struct Cli {
     /// No colour flag removes styling escapes from the output
     #[clap(long = "no-colour", alias = "no-color")]
     no_colour: bool,
+    /// Name of an alternative registry configured in cargo's config.toml
+    #[clap(long, value_name = "NAME", conflicts_with = "index_url")]
+    registry: Option<String>,
+    /// Url of an alternative registry index (sparse indices use the `sparse+` prefix, others are fetched with `git`)
+    #[clap(long = "index-url", value_name = "URL")]
+    index_url: Option<String>,
+    /// Path of a local registry, such as one written by `setup`, to query instead of a remote index
+    #[clap(
+        long = "local-registry",
+        value_name = "PATH",
+        conflicts_with_all = ["registry", "index_url"]
+    )]
+    local_registry: Option<PathBuf>,
+    /// Answer only from index entries already cached by cargo, without network access
+    #[clap(long, env = "KDEETS_OFFLINE")]
+    offline: bool,
+    /// Answer from the cache, without revalidating, for index entries fetched within this many seconds
+    #[clap(long = "max-age", value_name = "SECONDS", env = "KDEETS_MAX_AGE")]
+    max_age: Option<u64>,
+    /// Format of the output: human readable text or a document with a stable schema
+    #[clap(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value_t = OutputFormat::Text,
+        env = "KDEETS_FORMAT"
+    )]
+    format: OutputFormat,
     /// Force the calculation of the version number
     #[command(subcommand)]
     command: Commands,
@@ -25,9 +58,15 @@ enum Commands {
     /// Query crates.io for maximum Rust version for a crate
     #[clap(name = "rust")]
     RustVersions(RustVersions),
+    /// Compare the dependencies, features and Rust version of two versions of a crate
+    #[clap(name = "diff")]
+    CrateDiff(CrateDiff),
     /// Setup local registry for a crate
     #[clap(name = "setup")]
     Setup(Setup),
+    /// Manage a local registry written by setup
+    #[clap(name = "registry")]
+    RegistryCommand(RegistryCommand),
 }
 
 fn main() {
@@ -36,10 +75,26 @@ fn main() {
     let mut builder = get_logging(args.logging.log_level_filter());
     builder.init();
 
+    let registry = match args.local_registry {
+        Some(path) => Registry::Local(path),
+        None => Registry::new(args.registry, args.index_url),
+    };
+    let options = IndexOptions::new(registry)
+        .offline(args.offline)
+        .max_age(args.max_age.map(Duration::from_secs));
+
     let result = match args.command {
-        Commands::CrateVersions(mut crate_versions) => crate_versions.run(args.no_colour),
-        Commands::RustVersions(rust_versions) => rust_versions.run(),
-        Commands::Setup(setup) => setup.run(),
+        Commands::CrateVersions(mut crate_versions) => {
+            crate_versions.run(args.no_colour, &options, args.format)
+        }
+        Commands::RustVersions(rust_versions) => {
+            rust_versions.run(args.no_colour, &options, args.format)
+        }
+        Commands::CrateDiff(mut crate_diff) => {
+            crate_diff.run(args.no_colour, &options, args.format)
+        }
+        Commands::Setup(setup) => setup.run(&options, args.format),
+        Commands::RegistryCommand(registry) => registry.run(&options, args.format),
     };
 
     match result {
@@ -120,6 +175,108 @@ mod tests {
         assert_eq!(debug.logging.log_level(), Some(log::Level::Trace));
     }
 
+    #[test]
+    fn test_cli_offline() {
+        let cli = Cli::try_parse_from(["kdeets", "--offline", "crate", "some_crate"]).unwrap();
+        assert!(cli.offline);
+    }
+
+    #[test]
+    fn test_cli_max_age() {
+        let cli =
+            Cli::try_parse_from(["kdeets", "--max-age", "300", "crate", "some_crate"]).unwrap();
+        assert_eq!(cli.max_age, Some(300));
+    }
+
+    #[test]
+    fn test_cli_format() {
+        let cli = Cli::try_parse_from(["kdeets", "crate", "some_crate"]).unwrap();
+        assert_eq!(cli.format, OutputFormat::Text);
+
+        let cli =
+            Cli::try_parse_from(["kdeets", "--format", "json", "rust", "some_crate"]).unwrap();
+        assert_eq!(cli.format, OutputFormat::Json);
+
+        let result = Cli::try_parse_from(["kdeets", "--format", "xml", "crate", "some_crate"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cli_registry_defaults_to_crates_io() {
+        let cli = Cli::try_parse_from(["kdeets", "crate", "some_crate"]).unwrap();
+        assert_eq!(
+            Registry::new(cli.registry, cli.index_url),
+            Registry::CratesIo
+        );
+    }
+
+    #[test]
+    fn test_cli_registry_name() {
+        let cli =
+            Cli::try_parse_from(["kdeets", "--registry", "my-registry", "rust", "some_crate"])
+                .unwrap();
+        assert_eq!(
+            Registry::new(cli.registry, cli.index_url),
+            Registry::Named("my-registry".to_string())
+        );
+    }
+
+    #[test]
+    fn test_cli_index_url() {
+        let cli = Cli::try_parse_from([
+            "kdeets",
+            "--index-url",
+            "sparse+http://127.0.0.1:8080/",
+            "setup",
+            "some_crate",
+        ])
+        .unwrap();
+        assert_eq!(
+            Registry::new(cli.registry, cli.index_url),
+            Registry::Url("sparse+http://127.0.0.1:8080/".to_string())
+        );
+    }
+
+    #[test]
+    fn test_cli_registry_conflicts_with_index_url() {
+        let result = Cli::try_parse_from([
+            "kdeets",
+            "--registry",
+            "my-registry",
+            "--index-url",
+            "sparse+http://127.0.0.1:8080/",
+            "crate",
+            "some_crate",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cli_local_registry() {
+        let cli = Cli::try_parse_from([
+            "kdeets",
+            "--local-registry",
+            "tests/registry",
+            "diff",
+            "forestry",
+            "1.4.0",
+            "1.4.1",
+        ])
+        .unwrap();
+        assert_eq!(cli.local_registry, Some(PathBuf::from("tests/registry")));
+
+        let result = Cli::try_parse_from([
+            "kdeets",
+            "--local-registry",
+            "tests/registry",
+            "--registry",
+            "my-registry",
+            "crate",
+            "some_crate",
+        ]);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_cli_invalid_args() {
         let result = Cli::try_parse_from(["kdeets", "--invalid-flag", "crate", "some_crate"]);
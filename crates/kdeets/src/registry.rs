@@ -0,0 +1,330 @@
+//! Selection of the registry index queried by kdeets
+
+use std::time::Duration;
+
+use crate::{ComboIndex, Error, HttpCache};
+
+use tame_index::{IndexLocation, IndexUrl, Path, PathBuf};
+
+pub(crate) use credentials::Token;
+
+mod credentials;
+
+/// The registry whose index is queried.
+///
+/// Defaults to the crates.io sparse index. Named registries are resolved in the
+/// same way as cargo, using the `[registries]` table of cargo's `config.toml`
+/// (and any `CARGO_REGISTRIES_<NAME>_INDEX` override or source replacement).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Registry {
+    /// The crates.io sparse index
+    #[default]
+    CratesIo,
+    /// A registry named in the `[registries]` table of cargo's configuration
+    Named(String),
+    /// An index url; sparse indices must carry the `sparse+` prefix
+    Url(String),
+    /// A local registry directory, such as one written by `kdeets setup`
+    Local(PathBuf),
+}
+
+/// How the index of a registry is queried
+///
+/// Defaults to querying the crates.io sparse index over the network,
+/// revalidating every entry in kdeets' HTTP cache.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexOptions {
+    registry: Registry,
+    offline: bool,
+    max_age: Option<Duration>,
+}
+
+impl IndexOptions {
+    /// Creates the options for querying the registry
+    pub fn new(registry: Registry) -> Self {
+        Self {
+            registry,
+            ..Default::default()
+        }
+    }
+
+    /// Answers only from index entries already cached on disk, without any
+    /// network access
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Answers from kdeets' HTTP cache, without a request to the registry, for
+    /// entries fetched more recently than the maximum age
+    pub fn max_age(mut self, max_age: Option<Duration>) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// The registry whose index is queried
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// Opens the index of the registry, authenticating requests with the
+    /// registry's token when its index requires it.
+    ///
+    /// When offline the index answers only from entries already cached on disk.
+    pub(crate) fn combo_index(&self) -> Result<ComboIndex, Error> {
+        if let Registry::Local(path) = &self.registry {
+            return local_combo_index(path);
+        }
+        let location = self.registry.index_location()?;
+        if self.offline {
+            return crate::get_cached_combo_index(location);
+        }
+        let token = self.registry.token()?;
+        let cache = HttpCache::in_cargo_home(self.max_age)?;
+        crate::get_remote_combo_index(location, token.as_ref(), Some(cache))
+    }
+}
+
+impl Registry {
+    /// Creates the registry selection from the `--registry` and `--index-url`
+    /// options, falling back to crates.io when neither is given.
+    pub fn new(name: Option<String>, index_url: Option<String>) -> Self {
+        match (name, index_url) {
+            (_, Some(url)) => Self::Url(url),
+            (Some(name), None) => Self::Named(name),
+            (None, None) => Self::CratesIo,
+        }
+    }
+
+    /// Resolves the registry to the url of its index.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::TameIndex`] if a named registry cannot be found in
+    /// cargo's configuration.
+    pub fn index_url(&self) -> Result<IndexUrl<'static>, Error> {
+        self.resolve(None, None)
+    }
+
+    /// Resolves the registry to an [`IndexLocation`] using cargo's home
+    /// directory for the local cache of the index.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::TameIndex`] if a named registry cannot be found in
+    /// cargo's configuration.
+    pub fn index_location(&self) -> Result<IndexLocation<'static>, Error> {
+        Ok(IndexLocation::new(self.index_url()?))
+    }
+
+    /// Finds the token for the registry in the same places as cargo.
+    ///
+    /// An index url is looked up by the name it is configured with in the
+    /// `[registries]` of the cargo config files, and has no token when it is
+    /// not configured. crates.io never requires authentication to read its
+    /// index, and a local registry is read from disk.
+    pub(crate) fn token(&self) -> Result<Option<Token>, Error> {
+        let env = |var: &str| std::env::var(var).ok();
+        match self {
+            Self::Named(name) if name != "crates-io" => {
+                credentials::token_for_registry(name, env, &tame_index::utils::cargo_home()?)
+            }
+            Self::Url(url) => {
+                let cargo_home = tame_index::utils::cargo_home()?;
+                let config_root = std::env::current_dir()?;
+                match credentials::registry_name_for_url(url, &config_root, &cargo_home)? {
+                    Some(name) => credentials::token_for_registry(&name, env, &cargo_home),
+                    None => Ok(None),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// The name the registry is given in manifests, `crates-io` for
+    /// crates.io, or `None` for a local registry or an index url that is not
+    /// named in the `[registries]` of the cargo config files.
+    pub(crate) fn name(&self) -> Result<Option<String>, Error> {
+        match self {
+            Self::CratesIo => Ok(Some("crates-io".to_string())),
+            Self::Named(name) => Ok(Some(name.clone())),
+            Self::Url(url) => credentials::registry_name_for_url(
+                url,
+                &std::env::current_dir()?,
+                &tame_index::utils::cargo_home()?,
+            ),
+            Self::Local(_) => Ok(None),
+        }
+    }
+
+    /// Whether packages from the source, as recorded in a lockfile, are read
+    /// from this registry. crates.io is recorded by its git or its sparse
+    /// index url, and a local registry is taken to hold packages from every
+    /// source.
+    pub(crate) fn serves_source(&self, source: &str) -> Result<bool, Error> {
+        if let Self::Local(_) = self {
+            return Ok(true);
+        }
+        let source = source.strip_prefix("registry+").unwrap_or(source);
+        let index_url = self.index_url()?;
+        let is_crates_io = |url: &str| {
+            let url = credentials::normalise_url(url);
+            url == credentials::normalise_url(tame_index::CRATES_IO_INDEX)
+                || url == credentials::normalise_url(tame_index::CRATES_IO_HTTP_INDEX)
+        };
+        Ok(
+            credentials::normalise_url(source) == credentials::normalise_url(index_url.as_str())
+                || is_crates_io(source) && is_crates_io(index_url.as_str()),
+        )
+    }
+
+    pub(crate) fn resolve(
+        &self,
+        config_root: Option<PathBuf>,
+        cargo_home: Option<&Path>,
+    ) -> Result<IndexUrl<'static>, Error> {
+        let url = match self {
+            Self::CratesIo => IndexUrl::CratesIoSparse,
+            Self::Named(name) if name == "crates-io" => IndexUrl::CratesIoSparse,
+            Self::Named(name) => IndexUrl::for_registry_name(config_root, cargo_home, name)?,
+            Self::Url(url) => IndexUrl::NonCratesIo(url.clone().into()),
+            Self::Local(path) => IndexUrl::Local(path.clone().into()),
+        };
+        log::debug!("Using registry index: {}", url.as_str());
+        Ok(url)
+    }
+}
+
+/// Opens a local registry, which is read from disk and never cached
+fn local_combo_index(path: &Path) -> Result<ComboIndex, Error> {
+    if !path.join("index").is_dir() {
+        return Err(Error::LocalRegistryNotFound(path.to_string()));
+    }
+    Ok(ComboIndex::local(path.to_path_buf())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn cargo_home_with_config(config: &str) -> (tempfile::TempDir, PathBuf) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cargo_home = PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        fs::write(cargo_home.join("config.toml"), config).unwrap();
+        (temp_dir, cargo_home)
+    }
+
+    #[test]
+    fn test_missing_local_registry_names_the_path() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = PathBuf::from_path_buf(temp_dir.path().join("missing")).unwrap();
+        let result = local_combo_index(&path);
+        assert!(
+            matches!(result, Err(Error::LocalRegistryNotFound(ref missing)) if missing.as_str() == path.as_str())
+        );
+    }
+
+    #[test]
+    fn test_registry_new_defaults_to_crates_io() {
+        assert_eq!(Registry::new(None, None), Registry::CratesIo);
+    }
+
+    #[test]
+    fn test_registry_new_named() {
+        let registry = Registry::new(Some("my-registry".to_string()), None);
+        assert_eq!(registry, Registry::Named("my-registry".to_string()));
+    }
+
+    #[test]
+    fn test_registry_new_url() {
+        let registry = Registry::new(None, Some("sparse+http://127.0.0.1/".to_string()));
+        assert_eq!(
+            registry,
+            Registry::Url("sparse+http://127.0.0.1/".to_string())
+        );
+    }
+
+    #[test]
+    fn test_crates_io_resolves_to_sparse_index() {
+        let url = Registry::CratesIo.index_url().unwrap();
+        assert!(matches!(url, IndexUrl::CratesIoSparse));
+    }
+
+    #[test]
+    fn test_named_crates_io_resolves_to_sparse_index() {
+        let url = Registry::Named("crates-io".to_string())
+            .index_url()
+            .unwrap();
+        assert!(matches!(url, IndexUrl::CratesIoSparse));
+    }
+
+    #[test]
+    fn test_url_resolves_to_non_crates_io() {
+        let url = Registry::Url("sparse+http://127.0.0.1:8080/".to_string())
+            .index_url()
+            .unwrap();
+        assert_eq!(url.as_str(), "sparse+http://127.0.0.1:8080/");
+        assert!(url.is_sparse());
+    }
+
+    #[test]
+    fn test_named_registry_resolved_from_cargo_config() {
+        let (temp_dir, cargo_home) = cargo_home_with_config(
+            "[registries]\nmy-registry = { index = \"sparse+http://127.0.0.1:8080/\" }\n",
+        );
+        let config_root = PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+
+        let url = Registry::Named("my-registry".to_string())
+            .resolve(Some(config_root), Some(&cargo_home))
+            .unwrap();
+
+        assert_eq!(url.as_str(), "sparse+http://127.0.0.1:8080/");
+    }
+
+    #[test]
+    fn test_index_options_default() {
+        let options = IndexOptions::default();
+        assert_eq!(options.registry(), &Registry::CratesIo);
+        assert!(!options.offline);
+        assert_eq!(options.max_age, None);
+    }
+
+    #[test]
+    fn test_index_options_builder() {
+        let registry = Registry::Named("my-registry".to_string());
+        let options = IndexOptions::new(registry.clone())
+            .offline(true)
+            .max_age(Some(Duration::from_secs(300)));
+        assert_eq!(options.registry(), &registry);
+        assert!(options.offline);
+        assert_eq!(options.max_age, Some(Duration::from_secs(300)));
+    }
+
+    #[test]
+    fn test_crates_io_has_no_token() {
+        assert_eq!(Registry::CratesIo.token().unwrap(), None);
+        assert_eq!(
+            Registry::Named("crates-io".to_string()).token().unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_url_has_no_token() {
+        let registry = Registry::Url("sparse+http://127.0.0.1:8080/".to_string());
+        assert_eq!(registry.token().unwrap(), None);
+    }
+
+    #[test]
+    fn test_unknown_named_registry_is_an_error() {
+        let (temp_dir, cargo_home) = cargo_home_with_config("[registries]\n");
+        let config_root = PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+
+        let result = Registry::Named("kdeets-unknown-registry".to_string())
+            .resolve(Some(config_root), Some(&cargo_home));
+
+        assert!(matches!(result, Err(Error::TameIndex(_))));
+    }
+}
@@ -0,0 +1,293 @@
+use std::fmt::Debug;
+
+use tame_index::Path;
+use tame_index::external::http::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+
+use crate::Error;
+
+/// A registry token.
+///
+/// The value is never displayed or logged; `Debug` output is redacted.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct Token(String);
+
+impl Token {
+    pub(crate) fn new(token: impl Into<String>) -> Self {
+        Self(token.into())
+    }
+
+    /// Headers authenticating a request with the token
+    pub(crate) fn headers(&self) -> Result<HeaderMap, tame_index::Error> {
+        let mut value = HeaderValue::from_str(&self.0).map_err(tame_index::HttpError::from)?;
+        value.set_sensitive(true);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, value);
+        Ok(headers)
+    }
+}
+
+impl Debug for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Token(<redacted>)")
+    }
+}
+
+/// Finds the token for a named registry in the same places as cargo.
+///
+/// 1. The `CARGO_REGISTRIES_<NAME>_TOKEN` environment variable
+/// 2. `registries.<name>.token` in `$CARGO_HOME/credentials.toml` (or the
+///    legacy `credentials` file)
+pub(crate) fn token_for_registry(
+    name: &str,
+    env: impl Fn(&str) -> Option<String>,
+    cargo_home: &Path,
+) -> Result<Option<Token>, Error> {
+    let env_var = format!(
+        "CARGO_REGISTRIES_{}_TOKEN",
+        name.to_uppercase().replace('-', "_")
+    );
+    if let Some(token) = env(&env_var) {
+        log::debug!("Using registry token from {env_var}");
+        return Ok(Some(Token::new(token)));
+    }
+
+    for file in ["credentials.toml", "credentials"] {
+        let path = cargo_home.join(file);
+        if !path.exists() {
+            continue;
+        }
+
+        let contents = std::fs::read_to_string(&path)?;
+        // The parse error is not passed on as it may quote the token
+        let credentials: toml::Table =
+            toml::from_str(&contents).map_err(|_| Error::InvalidCredentials(path.to_string()))?;
+        let token = credentials
+            .get("registries")
+            .and_then(|registries| registries.get(name))
+            .and_then(|registry| registry.get("token"))
+            .and_then(|token| token.as_str());
+
+        if let Some(token) = token {
+            log::debug!("Using registry token from {path}");
+            return Ok(Some(Token::new(token)));
+        }
+    }
+
+    log::debug!("No token found for registry {name}");
+    Ok(None)
+}
+
+/// Finds the name of the registry with the index url in the `[registries]`
+/// tables of the cargo config files, so a registry given by its url can use
+/// the token configured for its name.
+///
+/// The `.cargo/config.toml` files are searched from `config_root` upwards, as
+/// cargo does from the current directory, followed by `$CARGO_HOME`. Urls
+/// match whether or not they end with `/` or start with `sparse+`.
+pub(crate) fn registry_name_for_url(
+    url: &str,
+    config_root: &std::path::Path,
+    cargo_home: &Path,
+) -> Result<Option<String>, Error> {
+    let url = normalise_url(url);
+    let config_files = config_root
+        .ancestors()
+        .flat_map(|dir| ["config.toml", "config"].map(|file| dir.join(".cargo").join(file)))
+        .chain(["config.toml", "config"].map(|file| cargo_home.join(file).into_std_path_buf()));
+
+    for path in config_files {
+        if !path.is_file() {
+            continue;
+        }
+
+        let contents = std::fs::read_to_string(&path)?;
+        let Ok(config) = toml::from_str::<toml::Table>(&contents) else {
+            log::debug!("Skipping {}, which could not be parsed", path.display());
+            continue;
+        };
+        let name = config
+            .get("registries")
+            .and_then(|registries| registries.as_table())
+            .and_then(|registries| {
+                registries.iter().find_map(|(name, registry)| {
+                    let index = registry.get("index")?.as_str()?;
+                    (normalise_url(index) == url).then(|| name.clone())
+                })
+            });
+
+        if let Some(name) = name {
+            log::debug!("Found registry {name} for {url} in {}", path.display());
+            return Ok(Some(name));
+        }
+    }
+
+    log::debug!("No registry name found for {url}");
+    Ok(None)
+}
+
+pub(super) fn normalise_url(url: &str) -> &str {
+    let url = url.strip_prefix("sparse+").unwrap_or(url);
+    url.trim_end_matches('/')
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tame_index::PathBuf;
+
+    use super::*;
+
+    fn cargo_home_with_credentials(file: &str, credentials: &str) -> (tempfile::TempDir, PathBuf) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cargo_home = PathBuf::from_path_buf(temp_dir.path().to_path_buf()).unwrap();
+        fs::write(cargo_home.join(file), credentials).unwrap();
+        (temp_dir, cargo_home)
+    }
+
+    #[test]
+    fn test_token_debug_is_redacted() {
+        let token = Token::new("secret-token");
+        let debug = format!("{token:?}");
+        assert!(!debug.contains("secret-token"));
+        assert_eq!(debug, "Token(<redacted>)");
+    }
+
+    #[test]
+    fn test_token_header_is_sensitive() {
+        let headers = Token::new("secret-token").headers().unwrap();
+        let value = headers.get(AUTHORIZATION).unwrap();
+        assert!(value.is_sensitive());
+        assert_eq!(value, "secret-token");
+        assert!(!format!("{headers:?}").contains("secret-token"));
+    }
+
+    #[test]
+    fn test_token_from_environment() {
+        let (_temp_dir, cargo_home) = cargo_home_with_credentials(
+            "credentials.toml",
+            "[registries.my-registry]\ntoken = \"from-file\"\n",
+        );
+        let env =
+            |var: &str| (var == "CARGO_REGISTRIES_MY_REGISTRY_TOKEN").then(|| "from-env".into());
+
+        let token = token_for_registry("my-registry", env, &cargo_home).unwrap();
+
+        assert_eq!(token, Some(Token::new("from-env")));
+    }
+
+    #[test]
+    fn test_token_from_credentials_toml() {
+        let (_temp_dir, cargo_home) = cargo_home_with_credentials(
+            "credentials.toml",
+            "[registries.my-registry]\ntoken = \"from-file\"\n",
+        );
+
+        let token = token_for_registry("my-registry", |_| None, &cargo_home).unwrap();
+
+        assert_eq!(token, Some(Token::new("from-file")));
+    }
+
+    #[test]
+    fn test_token_from_legacy_credentials() {
+        let (_temp_dir, cargo_home) = cargo_home_with_credentials(
+            "credentials",
+            "[registries.my-registry]\ntoken = \"from-legacy\"\n",
+        );
+
+        let token = token_for_registry("my-registry", |_| None, &cargo_home).unwrap();
+
+        assert_eq!(token, Some(Token::new("from-legacy")));
+    }
+
+    #[test]
+    fn test_invalid_credentials_error_does_not_quote_token() {
+        let (_temp_dir, cargo_home) = cargo_home_with_credentials(
+            "credentials.toml",
+            "[registries.my-registry]\ntoken = \"secret-token\n",
+        );
+
+        let result = token_for_registry("my-registry", |_| None, &cargo_home);
+
+        let Err(e) = result else {
+            panic!("Expected an error, got {result:?}");
+        };
+        assert!(matches!(e, Error::InvalidCredentials(_)));
+        assert!(!e.to_string().contains("secret-token"));
+    }
+
+    fn config_root_with_config(config: &str) -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cargo_dir = temp_dir.path().join(".cargo");
+        fs::create_dir(&cargo_dir).unwrap();
+        fs::write(cargo_dir.join("config.toml"), config).unwrap();
+        temp_dir
+    }
+
+    #[test]
+    fn test_registry_name_for_url() {
+        let config_root = config_root_with_config(
+            "[registries.my-registry]\nindex = \"sparse+https://registry.example.com/index/\"\n",
+        );
+        let workspace = config_root.path().join("workspace");
+        fs::create_dir(&workspace).unwrap();
+        let (_temp_dir, cargo_home) = cargo_home_with_credentials("config.toml", "");
+
+        let name = registry_name_for_url(
+            "https://registry.example.com/index",
+            &workspace,
+            &cargo_home,
+        )
+        .unwrap();
+
+        assert_eq!(name.as_deref(), Some("my-registry"));
+    }
+
+    #[test]
+    fn test_registry_name_for_url_in_cargo_home() {
+        let config_root = tempfile::tempdir().unwrap();
+        let (_temp_dir, cargo_home) = cargo_home_with_credentials(
+            "config.toml",
+            "[registries.home-registry]\nindex = \"sparse+https://home.example.com/\"\n",
+        );
+
+        let name = registry_name_for_url(
+            "sparse+https://home.example.com/",
+            config_root.path(),
+            &cargo_home,
+        )
+        .unwrap();
+
+        assert_eq!(name.as_deref(), Some("home-registry"));
+    }
+
+    #[test]
+    fn test_registry_name_for_unknown_url() {
+        let config_root = config_root_with_config(
+            "[registries.my-registry]\nindex = \"sparse+https://registry.example.com/\"\n",
+        );
+        let (_temp_dir, cargo_home) = cargo_home_with_credentials("config.toml", "");
+
+        let name = registry_name_for_url(
+            "sparse+https://other.example.com/",
+            config_root.path(),
+            &cargo_home,
+        )
+        .unwrap();
+
+        assert_eq!(name, None);
+    }
+
+    #[test]
+    fn test_token_not_found() {
+        let (_temp_dir, cargo_home) = cargo_home_with_credentials(
+            "credentials.toml",
+            "[registries.other-registry]\ntoken = \"other\"\n",
+        );
+
+        let token = token_for_registry("my-registry", |_| None, &cargo_home).unwrap();
+
+        assert_eq!(token, None);
+    }
+}
@@ -0,0 +1,291 @@
+//! The Rust versions declared by every published version of a crate
+
+use colorful::Colorful;
+use semver::Version;
+use serde::Serialize;
+use tame_index::IndexKrate;
+
+use crate::{Error, Report, RustVersion};
+
+/// The Rust version declared by each published version of a crate, in semver
+/// order, and where it was bumped
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub(crate) struct RustVersionHistory {
+    name: String,
+    bumps: usize,
+    patch_release_bumps: usize,
+    versions: Vec<HistoryEntry>,
+}
+
+/// A published version of the crate and the Rust version it declares
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct HistoryEntry {
+    version: String,
+    rust_version: Option<RustVersion>,
+    yanked: bool,
+    /// The Rust version declared before this version, when this version
+    /// declares a higher one
+    bumped_from: Option<RustVersion>,
+    /// The version only changes the patch number of the version before it
+    patch_release: bool,
+}
+
+impl HistoryEntry {
+    fn is_patch_release_bump(&self) -> bool {
+        self.bumped_from.is_some() && self.patch_release
+    }
+}
+
+impl RustVersionHistory {
+    pub(crate) fn new(index_crate: &IndexKrate) -> Result<Self, Error> {
+        let mut versions = vec![];
+        for version in &index_crate.versions {
+            versions.push((Version::parse(&version.version)?, version));
+        }
+        versions.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut entries: Vec<HistoryEntry> = vec![];
+        let mut declared: Option<RustVersion> = None;
+        let mut previous: Option<&Version> = None;
+        for (semver, version) in &versions {
+            let rust_version = RustVersion::of(version)?;
+            let bumped_from = declared.filter(|declared| rust_version > Some(*declared));
+            let patch_release = previous.is_some_and(|previous| {
+                previous.major == semver.major && previous.minor == semver.minor
+            });
+            entries.push(HistoryEntry {
+                version: version.version.to_string(),
+                rust_version,
+                yanked: version.yanked,
+                bumped_from,
+                patch_release,
+            });
+
+            declared = rust_version.or(declared);
+            previous = Some(semver);
+        }
+
+        Ok(Self {
+            name: index_crate.name().to_string(),
+            bumps: entries
+                .iter()
+                .filter(|entry| entry.bumped_from.is_some())
+                .count(),
+            patch_release_bumps: entries
+                .iter()
+                .filter(|entry| entry.is_patch_release_bump())
+                .count(),
+            versions: entries,
+        })
+    }
+
+    /// Renders the history as a table, highlighting the versions bumping the
+    /// Rust version unless `no_colour` is set
+    pub(crate) fn table(&self, no_colour: bool) -> String {
+        const VERSION: &str = "Version";
+        const RUST_VERSION: &str = "Rust version";
+        const YANKED: &str = "Yanked";
+
+        let rust_version = |entry: &HistoryEntry| {
+            entry
+                .rust_version
+                .map_or_else(|| "-".to_string(), |rv| rv.to_string())
+        };
+        let version_width = self
+            .versions
+            .iter()
+            .map(|entry| entry.version.len())
+            .chain([VERSION.len()])
+            .max()
+            .unwrap_or_default();
+        let rust_version_width = self
+            .versions
+            .iter()
+            .map(|entry| rust_version(entry).len())
+            .chain([RUST_VERSION.len()])
+            .max()
+            .unwrap_or_default();
+
+        let header =
+            format!("{VERSION:version_width$}  {RUST_VERSION:rust_version_width$}  {YANKED}");
+        let header = if no_colour {
+            header
+        } else {
+            header.underlined().to_string()
+        };
+        let mut table = format!("    {header}\n");
+        for entry in &self.versions {
+            let yanked = match (entry.yanked, no_colour) {
+                (true, true) => "Yes".to_string(),
+                (false, true) => " No".to_string(),
+                (true, false) => "Yes".red().to_string(),
+                (false, false) => " No".green().to_string(),
+            };
+            let mut row = format!(
+                "    {:version_width$}  {:rust_version_width$}  {yanked}",
+                entry.version,
+                rust_version(entry)
+            );
+            if let Some(bumped_from) = entry.bumped_from {
+                let bump = if entry.patch_release {
+                    let bump = format!("bumped from {bumped_from} in a patch release");
+                    if no_colour {
+                        bump
+                    } else {
+                        bump.red().bold().to_string()
+                    }
+                } else {
+                    let bump = format!("bumped from {bumped_from}");
+                    if no_colour {
+                        bump
+                    } else {
+                        bump.yellow().to_string()
+                    }
+                };
+                row.push_str(&format!("  {bump}"));
+            }
+            table.push_str(&row);
+            table.push('\n');
+        }
+        table.push_str(&format!(
+            "    Rust version bumps: {} ({} in a patch release)\n",
+            self.bumps, self.patch_release_bumps
+        ));
+
+        table
+    }
+}
+
+/// A row of the CSV output: a version of the crate
+#[derive(Debug, Serialize)]
+pub(crate) struct HistoryRecord {
+    #[serde(rename = "crate")]
+    name: String,
+    version: String,
+    rust_version: Option<RustVersion>,
+    yanked: bool,
+    bumped_from: Option<RustVersion>,
+    patch_release: bool,
+}
+
+impl Report for RustVersionHistory {
+    type Record = HistoryRecord;
+
+    const CSV_HEADERS: &'static [&'static str] = &[
+        "crate",
+        "version",
+        "rust_version",
+        "yanked",
+        "bumped_from",
+        "patch_release",
+    ];
+
+    fn records(&self) -> Vec<Self::Record> {
+        self.versions
+            .iter()
+            .map(|entry| HistoryRecord {
+                name: self.name.clone(),
+                version: entry.version.clone(),
+                rust_version: entry.rust_version,
+                yanked: entry.yanked,
+                bumped_from: entry.bumped_from,
+                patch_release: entry.patch_release,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A crate whose versions, published out of semver order, bump the Rust
+    /// version in a minor release and in a patch release, and drop it once
+    fn history() -> RustVersionHistory {
+        let version = |version: &str, yanked: bool, rust_version: Option<&str>| {
+            let rust_version = rust_version
+                .map(|rust_version| format!(r#","rust_version":"{rust_version}""#))
+                .unwrap_or_default();
+            format!(
+                r#"{{"name":"bumpy","vers":"{version}","deps":[],"cksum":"0000000000000000000000000000000000000000000000000000000000000000","features":{{}},"yanked":{yanked}{rust_version}}}"#
+            )
+        };
+        let entries = [
+            version("1.0.0", false, Some("1.56")),
+            version("1.1.0", false, Some("1.60")),
+            version("1.0.1", false, Some("1.56")),
+            version("1.1.1", true, Some("1.70")),
+            version("1.1.2", false, None),
+            version("1.2.0", false, Some("1.70")),
+        ];
+        let index_crate = IndexKrate::from_slice(entries.join("\n").as_bytes()).unwrap();
+        RustVersionHistory::new(&index_crate).unwrap()
+    }
+
+    #[test]
+    fn test_history_bumps() {
+        let history = history();
+
+        let bumps = history
+            .versions
+            .iter()
+            .map(|entry| {
+                (
+                    entry.version.as_str(),
+                    entry.bumped_from.map(|rv| rv.to_string()),
+                    entry.patch_release,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            bumps,
+            [
+                ("1.0.0", None, false),
+                ("1.0.1", None, true),
+                ("1.1.0", Some("1.56".to_string()), false),
+                ("1.1.1", Some("1.60".to_string()), true),
+                ("1.1.2", None, true),
+                ("1.2.0", None, false),
+            ]
+        );
+        assert_eq!(history.bumps, 2);
+        assert_eq!(history.patch_release_bumps, 1);
+        assert!(history.versions[3].yanked);
+    }
+
+    #[test]
+    fn test_history_table() {
+        let table = history().table(false);
+
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 8);
+        assert!(lines[0].contains("Version  Rust version  Yanked"));
+        assert!(lines[1].starts_with("    1.0.0    1.56          "));
+        assert!(lines[3].contains("bumped from 1.56"));
+        assert!(!lines[3].contains("patch release"));
+        assert!(lines[4].contains("bumped from 1.60 in a patch release"));
+        assert!(lines[5].starts_with("    1.1.2    -             "));
+        assert_eq!(lines[7], "    Rust version bumps: 2 (1 in a patch release)");
+    }
+
+    #[test]
+    fn test_history_table_no_colour() {
+        let table = history().table(true);
+
+        assert!(!table.contains('\u{1b}'));
+        let lines = table.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "    Version  Rust version  Yanked");
+        assert!(lines[4].ends_with("  bumped from 1.60 in a patch release"));
+    }
+
+    #[test]
+    fn test_history_records() {
+        let history = history();
+
+        let records = history.records();
+
+        assert_eq!(records.len(), 6);
+        assert_eq!(records[2].name, "bumpy");
+        assert_eq!(records[2].bumped_from, Some(RustVersion::new(1, 56, None)));
+    }
+}
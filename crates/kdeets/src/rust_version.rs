@@ -0,0 +1,220 @@
+//! The Rust version declared by a crate in the `rust-version` field of its
+//! manifest
+
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use serde::{Serialize, Serializer};
+use tame_index::IndexVersion;
+
+use crate::Error;
+
+/// A Rust version of the form `major.minor` or `major.minor.patch`.
+///
+/// Versions are compared numerically, with a missing patch number counting as
+/// zero, so `1.9` is lower than `1.70` and `1.60` equals `1.60.0`. A version is
+/// displayed as it was written.
+///
+/// # Examples
+///
+/// ```
+/// use kdeets_lib::RustVersion;
+///
+/// # fn main() -> Result<(), kdeets_lib::Error> {
+/// let older: RustVersion = "1.9".parse()?;
+/// let newer: RustVersion = "1.70".parse()?;
+/// assert!(older < newer);
+/// assert_eq!("1.60".parse::<RustVersion>()?, "1.60.0".parse()?);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RustVersion {
+    major: u64,
+    minor: u64,
+    patch: Option<u64>,
+}
+
+impl RustVersion {
+    /// Creates the Rust version `major.minor`, or `major.minor.patch` when a
+    /// patch number is given
+    pub fn new(major: u64, minor: u64, patch: Option<u64>) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parses the Rust version declared by a version of a crate, if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidRustVersion`], naming the crate version, if the
+    /// declared Rust version is malformed.
+    pub(crate) fn of(version: &IndexVersion) -> Result<Option<Self>, Error> {
+        let Some(rust_version) = &version.rust_version else {
+            return Ok(None);
+        };
+
+        rust_version.parse().map(Some).map_err(|_| {
+            Error::InvalidRustVersion(format!(
+                "{rust_version} (declared by {} {})",
+                version.name, version.version
+            ))
+        })
+    }
+
+    fn key(&self) -> (u64, u64, u64) {
+        (self.major, self.minor, self.patch.unwrap_or_default())
+    }
+}
+
+impl FromStr for RustVersion {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |part: &str| {
+            if part.is_empty() || !part.bytes().all(|byte| byte.is_ascii_digit()) {
+                return None;
+            }
+            part.parse::<u64>().ok()
+        };
+
+        let parts = s.split('.').map(number).collect::<Option<Vec<_>>>();
+        match parts.as_deref() {
+            Some([major, minor]) => Ok(Self::new(*major, *minor, None)),
+            Some([major, minor, patch]) => Ok(Self::new(*major, *minor, Some(*patch))),
+            _ => Err(Error::InvalidRustVersion(s.to_string())),
+        }
+    }
+}
+
+impl Display for RustVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+        if let Some(patch) = self.patch {
+            write!(f, ".{patch}")?;
+        }
+        Ok(())
+    }
+}
+
+impl PartialEq for RustVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for RustVersion {}
+
+impl PartialOrd for RustVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RustVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl Hash for RustVersion {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl Serialize for RustVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rust_version(s: &str) -> RustVersion {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(rust_version("1.70"), RustVersion::new(1, 70, None));
+        assert_eq!(rust_version("1.70.1"), RustVersion::new(1, 70, Some(1)));
+    }
+
+    #[test]
+    fn test_parse_malformed() {
+        for malformed in [
+            "",
+            "1",
+            "1.",
+            ".70",
+            "1.70.0.1",
+            "1.x",
+            "1.+5",
+            "1.70-beta",
+            " 1.70",
+        ] {
+            let result = malformed.parse::<RustVersion>();
+            assert!(
+                matches!(result, Err(Error::InvalidRustVersion(ref value)) if value == malformed),
+                "{malformed:?} parsed as {result:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_numeric_ordering() {
+        assert!(rust_version("1.9") < rust_version("1.70"));
+        assert!(rust_version("1.70") < rust_version("1.70.1"));
+        assert!(rust_version("2.0") > rust_version("1.99.9"));
+        assert_eq!(
+            ["1.9", "1.70", "1.56.1"].map(rust_version).iter().max(),
+            Some(&rust_version("1.70"))
+        );
+    }
+
+    #[test]
+    fn test_missing_patch_is_zero() {
+        assert_eq!(rust_version("1.60"), rust_version("1.60.0"));
+        assert_eq!(
+            rust_version("1.60").cmp(&rust_version("1.60.0")),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn test_display_as_written() {
+        assert_eq!(rust_version("1.60").to_string(), "1.60");
+        assert_eq!(rust_version("1.60.0").to_string(), "1.60.0");
+        assert_eq!(
+            serde_json::to_string(&rust_version("1.70")).unwrap(),
+            "\"1.70\""
+        );
+    }
+
+    #[test]
+    fn test_of_index_version() {
+        let index_crate = crate::diff::tests::two_versions();
+        assert_eq!(
+            RustVersion::of(&index_crate.versions[0]).unwrap(),
+            Some(rust_version("1.60"))
+        );
+
+        let json = serde_json::to_string(&index_crate.versions[0])
+            .unwrap()
+            .replace(r#""rust_version":"1.60""#, r#""rust_version":"1.sixty""#);
+        let version: IndexVersion = serde_json::from_str(&json).unwrap();
+        let error = RustVersion::of(&version).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid Rust version 1.sixty (declared by diffed 1.2.0), expected major.minor or major.minor.patch"
+        );
+    }
+}
@@ -1,81 +1,679 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt::Display;
+use std::rc::Rc;
 
-use crate::{Error, HEADER, LINE_CHAR};
+use crate::{
+    DependencyKind, Error, HEADER, IndexOptions, LINE_CHAR, OutputFormat, Report, RustVersion,
+};
 
 use crate::ComboIndex;
+use crate::client;
+use crate::rust_history::RustVersionHistory;
+use crate::walk::{DependencyClosure, WalkOptions, matching_version};
 use clap::Parser;
 use clap_verbosity::Verbosity;
 use colorful::Colorful;
 use semver::{Version, VersionReq};
-use smol_str::SmolStr;
-use tame_index::{IndexKrate, KrateName, index::FileLock};
+use serde::Serialize;
+use tame_index::krate::IndexDependency;
+use tame_index::{IndexKrate, IndexVersion, KrateName, index::FileLock};
 
 #[derive(Parser, Debug, Default)]
-#[clap(author, version, about, long_about = None)]
+#[clap(author, about, long_about = None)]
 pub struct RustVersions {
     #[clap(flatten)]
     logging: Verbosity,
     /// The name of the crate
     crate_: String,
+    /// Include every optional dependency, not only those enabled by default or requested features
+    #[clap(long = "optional")]
+    optional: bool,
+    /// Include the dev dependencies of the crate, used by its tests, examples and benchmarks
+    #[clap(long = "dev")]
+    dev: bool,
+    /// Exclude build dependencies, used by build scripts
+    #[clap(long = "no-build")]
+    no_build: bool,
+    /// Resolve each dependency to the lowest matching version, as cargo's `-Z minimal-versions` does, instead of the highest
+    #[clap(long = "minimal-versions")]
+    minimal_versions: bool,
+    /// Show the dependency graph walked, with the Rust version each crate declares
+    #[clap(long = "tree")]
+    tree: bool,
+    /// The version to analyse instead of the most recent version
+    #[clap(long = "version", value_name = "VERSION", conflicts_with_all = ["req", "toolchain"])]
+    version: Option<String>,
+    /// Analyse the highest version, that is not yanked, matching the semver requirement
+    #[clap(long = "req", value_name = "REQ", conflicts_with = "toolchain")]
+    req: Option<VersionReq>,
+    /// Analyse the highest version, that is not yanked or a pre-release, declaring a Rust version no higher than the toolchain
+    #[clap(long = "toolchain", value_name = "RUST_VERSION")]
+    toolchain: Option<RustVersion>,
+    /// List every published version with the Rust version it declares, highlighting where it was bumped
+    #[clap(long = "history", conflicts_with_all = ["version", "req", "toolchain", "tree"])]
+    history: bool,
+    /// With `--toolchain`, also require the Rust versions declared by the dependency graph to be no higher than the toolchain
+    #[clap(long = "with-dependencies", requires = "toolchain")]
+    with_dependencies: bool,
 }
 
 impl RustVersions {
-    pub fn run(&self) -> Result<String, Error> {
+    pub fn run(
+        &self,
+        no_colour: bool,
+        options: &IndexOptions,
+        format: OutputFormat,
+    ) -> Result<String, Error> {
         log::info!("Getting details for crate: {}", self.crate_);
         let lock = FileLock::unlocked();
-        let index = crate::get_remote_combo_index()?;
-        let index_crate = index.krate(KrateName::crates_io(&self.crate_)?, true, &lock)?;
+        let index = options.combo_index()?;
+        let index_crate = index.krate(KrateName::cargo(&self.crate_)?, true, &lock)?;
 
         let Some(index_crate) = index_crate else {
-            return Err(Error::CrateNotFoundOnIndex);
+            return Err(index.not_found(&self.crate_));
         };
 
-        let mut output = RustVersionOutput::new(index_crate);
+        if self.history {
+            let history = RustVersionHistory::new(&index_crate)?;
+            return format.render(&history, || {
+                let mut output = RustVersionOutput::new(index_crate, no_colour);
+                output.set_history(&history);
+                Ok(output.to_string())
+            });
+        }
+
+        let version = self.select_version(&index, &index_crate)?;
+        let mut walk = RustVersionWalk::new(&index, self.walk_options());
+        let dependencies = walk.walk(&index_crate, version)?;
+        let report = RustVersionReport::new(version, dependencies, &walk)?;
+        let tree = self.tree.then(|| walk.tree(version));
+        let selected = self
+            .selection_label()
+            .map(|label| (label, version.version.to_string()));
+
+        format.render(&report, || {
+            let mut output = RustVersionOutput::new(index_crate, no_colour);
+
+            output.set_rust_version(
+                selected
+                    .as_ref()
+                    .map(|(label, version)| (label.as_str(), version.as_str())),
+            )?;
+
+            output.set_minimum_rust_version(&report);
+
+            if let Some(tree) = &tree {
+                output.set_tree(tree);
+            }
+
+            Ok(output.to_string())
+        })
+    }
+
+    /// The version of the crate to analyse: the version, the highest matching
+    /// the requirement or the highest compatible with the toolchain if any is
+    /// given, otherwise the most recent
+    fn select_version<'k>(
+        &self,
+        index: &ComboIndex,
+        index_crate: &'k IndexKrate,
+    ) -> Result<&'k IndexVersion, Error> {
+        if let Some(version) = &self.version {
+            return client::select_version(index_crate, Some(version));
+        }
+        if let Some(req) = &self.req {
+            return matching_version(index_crate, req, false)?.ok_or_else(|| {
+                Error::NoMatchingVersion(
+                    index_crate.name().to_string(),
+                    format!("requirement {req}"),
+                )
+            });
+        }
+        if let Some(toolchain) = self.toolchain {
+            return self.compatible_version(index, index_crate, toolchain);
+        }
+
+        Ok(index_crate.most_recent_version())
+    }
+
+    /// Finds the highest version of the crate, that is not yanked or a
+    /// pre-release, declaring a Rust version no higher than the toolchain.
+    ///
+    /// A version that does not declare a Rust version is taken to be
+    /// compatible, as cargo's resolver does. With `--with-dependencies` the
+    /// highest Rust version declared by its dependency graph must also be no
+    /// higher than the toolchain.
+    fn compatible_version<'k>(
+        &self,
+        index: &ComboIndex,
+        index_crate: &'k IndexKrate,
+        toolchain: RustVersion,
+    ) -> Result<&'k IndexVersion, Error> {
+        let mut candidates = vec![];
+        for version in index_crate
+            .versions
+            .iter()
+            .filter(|version| !version.yanked)
+        {
+            let semver = Version::parse(&version.version)?;
+            if semver.pre.is_empty() {
+                candidates.push((semver, version));
+            }
+        }
+        candidates.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+        let mut walk = RustVersionWalk::new(index, self.walk_options());
+        for (_, version) in candidates {
+            if RustVersion::of(version)?.is_some_and(|rust_version| rust_version > toolchain) {
+                continue;
+            }
+            if self.with_dependencies {
+                walk.walk(index_crate, version)?;
+                let minimum = walk.minimum_rust_version(version);
+                if minimum.is_some_and(|minimum| minimum > toolchain) {
+                    log::debug!(
+                        "{} {} needs Rust {:?} for its dependencies",
+                        version.name,
+                        version.version,
+                        minimum
+                    );
+                    continue;
+                }
+            }
+            return Ok(version);
+        }
+
+        let mut requirement = format!("Rust version {toolchain} or lower");
+        if self.with_dependencies {
+            requirement.push_str(", including its dependencies");
+        }
+        Err(Error::NoMatchingVersion(
+            index_crate.name().to_string(),
+            requirement,
+        ))
+    }
+
+    /// How the selected version is labelled in the text output, if a version
+    /// was selected
+    fn selection_label(&self) -> Option<String> {
+        if let Some(toolchain) = self.toolchain {
+            Some(format!("Highest version for Rust {toolchain}"))
+        } else if self.version.is_some() || self.req.is_some() {
+            Some("Version".to_string())
+        } else {
+            None
+        }
+    }
+
+    fn walk_options(&self) -> WalkOptions {
+        WalkOptions {
+            optional: self.optional,
+            dev: self.dev,
+            build: !self.no_build,
+            minimal_versions: self.minimal_versions,
+            ..WalkOptions::default()
+        }
+    }
+}
 
-        output.set_rust_version()?;
+/// The Rust versions declared by a version of a crate and its dependency graph
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct RustVersionReport {
+    name: String,
+    version: String,
+    rust_version: Option<RustVersion>,
+    minimum_rust_version: Option<RustVersion>,
+    crates_inspected: usize,
+    crates_without_rust_version: usize,
+    constrained_by: Vec<CrateRustVersion>,
+    dependencies: Vec<DependencyRustVersion>,
+    crates: Vec<CrateRustVersion>,
+}
 
-        output.set_minimum_rust_version_required(&index)?;
+/// The Rust version declared by a crate version in the dependency graph, and
+/// the highest declared anywhere in its own dependency graph
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct CrateRustVersion {
+    name: String,
+    version: String,
+    rust_version: Option<RustVersion>,
+    minimum_rust_version: Option<RustVersion>,
+}
 
-        Ok(output.to_string())
+/// The Rust version declared by the version of a direct dependency matching
+/// the requirement, and the highest declared anywhere in its dependency graph
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct DependencyRustVersion {
+    name: String,
+    req: String,
+    kind: DependencyKind,
+    version: Option<String>,
+    rust_version: Option<RustVersion>,
+    minimum_rust_version: Option<RustVersion>,
+}
+
+impl RustVersionReport {
+    fn new(
+        version: &IndexVersion,
+        dependencies: Vec<DependencyRustVersion>,
+        walk: &RustVersionWalk,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            name: version.name.to_string(),
+            version: version.version.to_string(),
+            rust_version: RustVersion::of(version)?,
+            minimum_rust_version: walk.minimum_rust_version(version),
+            crates_inspected: walk.crates_inspected(),
+            crates_without_rust_version: walk.crates_without_rust_version(),
+            constrained_by: walk.constrained_by(version),
+            dependencies,
+            crates: walk.crates(),
+        })
     }
 }
 
-fn get_rust_version(
-    index: &ComboIndex,
-    name: &str,
-    version_reference: VersionReq,
-) -> Result<Option<SmolStr>, Error> {
-    let crate_name = KrateName::crates_io(name)?;
-    let lock = FileLock::unlocked();
-    let index_crate = index.krate(crate_name, true, &lock)?;
+/// A row of the CSV output: a dependency of the crate with the crate's own
+/// Rust versions repeated
+#[derive(Debug, Serialize)]
+struct DependencyRecord {
+    #[serde(rename = "crate")]
+    name: String,
+    version: String,
+    rust_version: Option<RustVersion>,
+    minimum_rust_version: Option<RustVersion>,
+    crates_inspected: usize,
+    dependency: String,
+    req: String,
+    kind: DependencyKind,
+    dependency_version: Option<String>,
+    dependency_rust_version: Option<RustVersion>,
+    dependency_minimum_rust_version: Option<RustVersion>,
+}
+
+impl Report for RustVersionReport {
+    type Record = DependencyRecord;
+
+    const CSV_HEADERS: &'static [&'static str] = &[
+        "crate",
+        "version",
+        "rust_version",
+        "minimum_rust_version",
+        "crates_inspected",
+        "dependency",
+        "req",
+        "kind",
+        "dependency_version",
+        "dependency_rust_version",
+        "dependency_minimum_rust_version",
+    ];
 
-    let Some(index_crate) = index_crate else {
-        return Err(Error::CrateNotFoundOnIndex);
-    };
+    fn records(&self) -> Vec<Self::Record> {
+        self.dependencies
+            .iter()
+            .map(|dep| DependencyRecord {
+                name: self.name.clone(),
+                version: self.version.clone(),
+                rust_version: self.rust_version,
+                minimum_rust_version: self.minimum_rust_version,
+                crates_inspected: self.crates_inspected,
+                dependency: dep.name.clone(),
+                req: dep.req.clone(),
+                kind: dep.kind,
+                dependency_version: dep.version.clone(),
+                dependency_rust_version: dep.rust_version,
+                dependency_minimum_rust_version: dep.minimum_rust_version,
+            })
+            .collect()
+    }
+}
+
+/// Walks the dependency graph of a crate version to find the highest Rust
+/// version declared anywhere in it.
+///
+/// Each crate is fetched from the index once and each crate version is walked
+/// once, however many times it is depended on. A dependency back onto a crate
+/// version still being walked closes a cycle and is not followed again.
+///
+/// The optional dependencies followed are those enabled by the features the
+/// crate version is built with, found by walking the dependency closure of
+/// the crate as `setup` does.
+pub(crate) struct RustVersionWalk<'i> {
+    options: WalkOptions,
+    closure: DependencyClosure<'i>,
+    walked: HashMap<(String, String), Walked>,
+    in_progress: HashSet<(String, String)>,
+    without_rust_version: HashSet<(String, String)>,
+}
+
+impl<'i> RustVersionWalk<'i> {
+    pub(crate) fn new(index: &'i ComboIndex, options: WalkOptions) -> Self {
+        Self {
+            closure: DependencyClosure::new(index, options.clone()),
+            options,
+            walked: HashMap::new(),
+            in_progress: HashSet::new(),
+            without_rust_version: HashSet::new(),
+        }
+    }
+
+    /// Walks the dependency graph of the crate version, returning the Rust
+    /// versions of each direct dependency followed
+    pub(crate) fn walk(
+        &mut self,
+        index_crate: &IndexKrate,
+        version: &IndexVersion,
+    ) -> Result<Vec<DependencyRustVersion>, Error> {
+        // The features each crate version is built with depend on everything
+        // else in the graph of this version, so are found afresh for it
+        let index = self.closure.crates().index();
+        let crates = std::mem::replace(
+            &mut self.closure,
+            DependencyClosure::new(index, self.options.clone()),
+        )
+        .into_crates();
+        self.closure = DependencyClosure::with_crates(crates, self.options.clone());
+        self.closure.walk(index_crate, &[version])?;
+
+        let key = (version.name.to_string(), version.version.to_string());
+        self.in_progress.insert(key.clone());
+
+        let mut walked = Walked::new(RustVersion::of(version)?, self.enabled(version));
+        let mut dependencies = vec![];
+        for dep in self.followed(version, true) {
+            let req = dep.version_requirement();
+            let resolved = self.resolve(dep.crate_name(), &req)?;
+            log::debug!(
+                "    {}   {}  {:?}\n",
+                dep.crate_name(),
+                req,
+                resolved.as_ref().map(|resolved| &resolved.rust_version),
+            );
+            if let Some(resolved) = &resolved {
+                walked.depends_on(resolved);
+            }
+            dependencies.push(DependencyRustVersion {
+                name: dep.crate_name().to_string(),
+                req: req.to_string(),
+                kind: dep.kind().into(),
+                version: resolved.as_ref().map(|resolved| resolved.key.1.clone()),
+                rust_version: resolved.as_ref().and_then(|resolved| resolved.rust_version),
+                minimum_rust_version: resolved
+                    .as_ref()
+                    .and_then(|resolved| resolved.minimum_rust_version),
+            });
+        }
+
+        self.in_progress.remove(&key);
+        self.walked.insert(key, walked);
+
+        Ok(dependencies)
+    }
+
+    /// The highest Rust version declared by a walked crate version or anything
+    /// in its dependency graph
+    pub(crate) fn minimum_rust_version(&self, version: &IndexVersion) -> Option<RustVersion> {
+        let key = (version.name.to_string(), version.version.to_string());
+        self.walked.get(&key)?.minimum_rust_version
+    }
+
+    /// The chain of dependencies from a walked crate version to the crate
+    /// version declaring its minimum Rust version.
+    ///
+    /// The chain starts with the crate version itself and is empty when no
+    /// crate in its dependency graph declares a Rust version.
+    pub(crate) fn constrained_by(&self, version: &IndexVersion) -> Vec<CrateRustVersion> {
+        let mut key = (version.name.to_string(), version.version.to_string());
+        let mut seen = HashSet::new();
+        let mut chain = vec![];
+        while let Some(walked) = self.walked.get(&key) {
+            if walked.minimum_rust_version.is_none() || !seen.insert(key.clone()) {
+                break;
+            }
+            chain.push(self.crate_rust_version(&key, walked));
+            let Some(next) = &walked.constrained_by else {
+                break;
+            };
+            key = next.clone();
+        }
+        chain
+    }
+
+    /// The Rust versions of every crate version walked, ordered by name and
+    /// version
+    pub(crate) fn crates(&self) -> Vec<CrateRustVersion> {
+        let mut crates = self
+            .walked
+            .iter()
+            .map(|(key, walked)| self.crate_rust_version(key, walked))
+            .collect::<Vec<_>>();
+        crates.sort_by(|a, b| {
+            let semver = |version: &str| Version::parse(version).ok();
+            a.name
+                .cmp(&b.name)
+                .then_with(|| semver(&a.version).cmp(&semver(&b.version)))
+        });
+        crates
+    }
+
+    /// Renders the walked dependency graph of the crate version as a tree,
+    /// with the Rust version each crate version declares.
+    ///
+    /// A crate version already shown, or closing a cycle, is marked `(*)` and
+    /// its dependencies are not repeated.
+    pub(crate) fn tree(&self, version: &IndexVersion) -> Vec<String> {
+        let key = (version.name.to_string(), version.version.to_string());
+        let mut lines = vec![];
+        self.tree_node(&key, "", "", &mut HashSet::new(), &mut lines);
+        lines
+    }
+
+    fn tree_node(
+        &self,
+        key: &(String, String),
+        branch: &str,
+        indent: &str,
+        shown: &mut HashSet<(String, String)>,
+        lines: &mut Vec<String>,
+    ) {
+        let Some(walked) = self.walked.get(key) else {
+            return;
+        };
+        let rust_version = walked
+            .rust_version
+            .map_or_else(|| "not specified".to_string(), |rv| rv.to_string());
+        let first = shown.insert(key.clone());
+        let mut line = format!("{branch}{} {} (rust-version {rust_version})", key.0, key.1);
+        if !first && !walked.dependencies.is_empty() {
+            line.push_str(" (*)");
+        }
+        lines.push(line);
+        if !first {
+            return;
+        }
+
+        for (i, dependency) in walked.dependencies.iter().enumerate() {
+            let (branch, child_indent) = if i + 1 == walked.dependencies.len() {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            self.tree_node(
+                dependency,
+                &format!("{indent}{branch}"),
+                &format!("{indent}{child_indent}"),
+                shown,
+                lines,
+            );
+        }
+    }
+
+    /// The number of crate versions walked, including the crate itself
+    pub(crate) fn crates_inspected(&self) -> usize {
+        self.walked.len()
+    }
+
+    /// The number of dependencies walked that do not declare a Rust version
+    pub(crate) fn crates_without_rust_version(&self) -> usize {
+        self.without_rust_version.len()
+    }
 
-    for version in index_crate.versions {
-        if version_reference.matches(&Version::parse(&version.version)?) {
-            return Ok(version.rust_version);
+    fn crate_rust_version(&self, key: &(String, String), walked: &Walked) -> CrateRustVersion {
+        CrateRustVersion {
+            name: key.0.clone(),
+            version: key.1.clone(),
+            rust_version: walked.rust_version,
+            minimum_rust_version: walked.minimum_rust_version,
         }
     }
 
-    Ok(None)
+    /// The dependencies of the version that the walk follows
+    fn followed<'v>(
+        &self,
+        version: &'v IndexVersion,
+        root: bool,
+    ) -> impl Iterator<Item = &'v IndexDependency> + use<'v> {
+        let options = self.options.clone();
+        let enabled = self.enabled(version);
+        version
+            .dependencies()
+            .iter()
+            .filter(move |dep| options.follows(dep, root, &enabled))
+    }
+
+    /// The optional dependencies of the version enabled by the features it is
+    /// built with
+    fn enabled(&self, version: &IndexVersion) -> BTreeSet<String> {
+        self.closure.enabled(version).dependencies
+    }
+
+    /// Finds the version of the dependency matching the requirement and walks
+    /// its dependency graph
+    fn resolve(&mut self, name: &str, req: &VersionReq) -> Result<Option<Resolved>, Error> {
+        let index_crate = self.krate(name)?;
+        let Some(version) = matching_version(&index_crate, req, self.options.minimal_versions)?
+        else {
+            log::debug!("No version of {name} that is not yanked matches {req}");
+            return Ok(None);
+        };
+        let key = (name.to_string(), version.version.to_string());
+        let rust_version = RustVersion::of(version)?;
+        let resolved = |key, minimum_rust_version| {
+            Some(Resolved {
+                key,
+                rust_version,
+                minimum_rust_version,
+            })
+        };
+
+        let enabled = self.enabled(version);
+        if let Some(walked) = self
+            .walked
+            .get(&key)
+            .filter(|walked| walked.enabled == enabled)
+        {
+            return Ok(resolved(key, walked.minimum_rust_version));
+        }
+        if !self.in_progress.insert(key.clone()) {
+            log::debug!("Dependency cycle through {} {}", key.0, key.1);
+            return Ok(resolved(key, rust_version));
+        }
+        if rust_version.is_none() {
+            self.without_rust_version.insert(key.clone());
+        }
+
+        let mut walked = Walked::new(rust_version, enabled);
+        for dep in self.followed(version, false) {
+            if let Some(resolved) = self.resolve(dep.crate_name(), &dep.version_requirement())? {
+                walked.depends_on(&resolved);
+            }
+        }
+
+        self.in_progress.remove(&key);
+        let minimum = walked.minimum_rust_version;
+        self.walked.insert(key.clone(), walked);
+        Ok(resolved(key, minimum))
+    }
+
+    /// Fetches the crate from the index, or from the crates already fetched
+    fn krate(&mut self, name: &str) -> Result<Rc<IndexKrate>, Error> {
+        let crates = self.closure.crates();
+        let index_crate = crates.krate(name)?;
+        index_crate.ok_or_else(|| crates.index().not_found(name))
+    }
+}
+
+/// The version of a dependency matching its requirement
+struct Resolved {
+    /// The name and version of the crate
+    key: (String, String),
+    /// The Rust version declared by the version
+    rust_version: Option<RustVersion>,
+    /// The highest Rust version declared by the version or anything in its
+    /// dependency graph
+    minimum_rust_version: Option<RustVersion>,
+}
+
+/// A crate version that has been walked
+struct Walked {
+    /// The Rust version declared by the version
+    rust_version: Option<RustVersion>,
+    /// The highest Rust version declared by the version or anything in its
+    /// dependency graph
+    minimum_rust_version: Option<RustVersion>,
+    /// The dependency the minimum Rust version comes from, when it is not
+    /// declared by the version itself
+    constrained_by: Option<(String, String)>,
+    /// The dependencies followed, in the order they are declared
+    dependencies: Vec<(String, String)>,
+    /// The optional dependencies enabled when the version was walked
+    enabled: BTreeSet<String>,
+}
+
+impl Walked {
+    fn new(rust_version: Option<RustVersion>, enabled: BTreeSet<String>) -> Self {
+        Self {
+            rust_version,
+            minimum_rust_version: rust_version,
+            enabled,
+            constrained_by: None,
+            dependencies: vec![],
+        }
+    }
+
+    /// Records a dependency, which constrains the minimum Rust version when
+    /// it needs a higher one than anything recorded so far
+    fn depends_on(&mut self, resolved: &Resolved) {
+        if resolved.minimum_rust_version > self.minimum_rust_version {
+            self.minimum_rust_version = resolved.minimum_rust_version;
+            self.constrained_by = Some(resolved.key.clone());
+        }
+        if !self.dependencies.contains(&resolved.key) {
+            self.dependencies.push(resolved.key.clone());
+        }
+    }
 }
 
 #[derive(Debug)]
 struct RustVersionOutput {
     index_crate: IndexKrate,
+    no_colour: bool,
     header: String,
     rust_version: Option<String>,
     minimum_required_rust: Option<String>,
+    tree: Option<String>,
+    history: Option<String>,
 }
 
 impl RustVersionOutput {
-    fn new(index_crate: IndexKrate) -> Self {
+    fn new(index_crate: IndexKrate, no_colour: bool) -> Self {
         let mut header = String::from("\n  ");
         header.push_str(HEADER);
         header.push(' ');
-        header.push_str(index_crate.name().cyan().to_string().as_str());
+        if no_colour {
+            header.push_str(index_crate.name());
+        } else {
+            header.push_str(index_crate.name().cyan().to_string().as_str());
+        }
         header.push('.');
         header.push_str("\n  ");
         let mut i = 0;
@@ -87,31 +685,50 @@ impl RustVersionOutput {
 
         Self {
             index_crate,
+            no_colour,
             header,
             rust_version: None,
             minimum_required_rust: None,
+            tree: None,
+            history: None,
         }
     }
 
-    fn set_rust_version(&mut self) -> Result<(), Error> {
-        let mut rust_version = String::from("    Most recent version: ");
-        rust_version.push_str(
-            self.index_crate
-                .most_recent_version()
-                .version
-                .to_string()
-                .as_str(),
-        );
+    /// Sets the Rust version declared by the selected version, or by the most
+    /// recent version if none was selected
+    fn set_rust_version(&mut self, selected: Option<(&str, &str)>) -> Result<(), Error> {
+        let (label, version) = match selected {
+            Some((label, selected)) => {
+                let version = self
+                    .index_crate
+                    .versions
+                    .iter()
+                    .find(|version| version.version == selected)
+                    .ok_or_else(|| {
+                        Error::VersionNotFound(
+                            self.index_crate.name().to_string(),
+                            selected.to_string(),
+                        )
+                    })?;
+                (label, version)
+            }
+            None => (
+                "Most recent version",
+                self.index_crate.most_recent_version(),
+            ),
+        };
+        let mut rust_version = format!("    {label}: {}", version.version);
         rust_version.push_str(" (Rust version: ");
-        let rv = if let Some(rv) = &self.index_crate.most_recent_version().rust_version {
+        let rv = if let Some(rv) = RustVersion::of(version)? {
             rv.to_string()
         } else {
             "not specified".to_string()
+        };
+        if self.no_colour {
+            rust_version.push_str(&rv);
+        } else {
+            rust_version.push_str(&rv.blue().bold().to_string());
         }
-        .blue()
-        .bold()
-        .to_string();
-        rust_version.push_str(&rv);
         rust_version.push_str(")\n");
 
         self.rust_version = Some(rust_version);
@@ -119,49 +736,60 @@ impl RustVersionOutput {
         Ok(())
     }
 
-    fn set_minimum_rust_version_required(&mut self, index: &ComboIndex) -> Result<(), Error> {
-        let mut rust_versions = vec![];
-
-        let deps = self.index_crate.most_recent_version().dependencies();
-        for dep in deps {
-            let rust_version =
-                get_rust_version(index, dep.crate_name(), dep.version_requirement())?;
-            rust_versions.push(rust_version.clone());
-            log::debug!(
-                "    {}   {}  {:?}\n",
-                dep.crate_name(),
-                dep.version_requirement(),
-                rust_version,
-            );
-        }
-
-        let minimum_rust = rust_versions
-            .iter()
-            .filter_map(|rv_opt| rv_opt.as_ref())
-            .max();
-
+    fn set_minimum_rust_version(&mut self, report: &RustVersionReport) {
         let mut minimum_required_rust = String::from("    Minimum Rust version: ");
-        if let Some(minimum_rust) = minimum_rust {
-            minimum_required_rust.push_str(minimum_rust.to_string().as_str());
+        if let Some(minimum_rust) = &report.minimum_rust_version {
+            minimum_required_rust.push_str(&minimum_rust.to_string());
         } else {
             minimum_required_rust.push_str("not specified");
         }
 
-        if rust_versions.iter().any(|rv| rv.is_none()) {
+        if report.crates_without_rust_version > 0 {
             minimum_required_rust.push_str(" (");
-            minimum_required_rust.push_str(
-                " (WARNING: Some dependencies do not specify a Rust version)"
-                    .yellow()
-                    .to_string()
-                    .as_str(),
-            );
+            let warning = "WARNING: Some dependencies do not specify a Rust version";
+            if self.no_colour {
+                minimum_required_rust.push_str(warning);
+            } else {
+                minimum_required_rust.push_str(&warning.yellow().to_string());
+            }
             minimum_required_rust.push(')');
         }
-        minimum_required_rust.push('\n');
+        if let Some(constraining) = report.constrained_by.last() {
+            let chain = report
+                .constrained_by
+                .iter()
+                .map(|link| format!("{} {}", link.name, link.version))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            let rust_version = constraining
+                .rust_version
+                .map(|rv| rv.to_string())
+                .unwrap_or_default();
+            minimum_required_rust.push_str(&format!(
+                "\n    Set by: {chain} (rust-version {rust_version})"
+            ));
+        }
+        minimum_required_rust.push_str(&format!(
+            "\n    Crates inspected: {}\n",
+            report.crates_inspected
+        ));
 
         self.minimum_required_rust = Some(minimum_required_rust);
+    }
 
-        Ok(())
+    fn set_history(&mut self, history: &RustVersionHistory) {
+        self.history = Some(history.table(self.no_colour));
+    }
+
+    fn set_tree(&mut self, lines: &[String]) {
+        let mut tree = String::from("\n    Dependency tree:\n");
+        for line in lines {
+            tree.push_str("      ");
+            tree.push_str(line);
+            tree.push('\n');
+        }
+
+        self.tree = Some(tree);
     }
 }
 
@@ -174,6 +802,12 @@ impl Display for RustVersionOutput {
         if let Some(minimum_required_rust) = &self.minimum_required_rust {
             write!(f, "{minimum_required_rust}")?;
         }
+        if let Some(tree) = &self.tree {
+            write!(f, "{tree}")?;
+        }
+        if let Some(history) = &self.history {
+            write!(f, "{history}")?;
+        }
         Ok(())
     }
 }
@@ -231,7 +865,7 @@ mod tests {
             .unwrap()
             .unwrap();
 
-        let output = RustVersionOutput::new(index_crate);
+        let output = RustVersionOutput::new(index_crate, false);
 
         assert_eq!(output.to_string(), expected);
     }
@@ -248,15 +882,15 @@ mod tests {
             .unwrap()
             .unwrap();
 
-        let mut output = RustVersionOutput::new(index_crate);
-        output.set_rust_version().unwrap();
+        let mut output = RustVersionOutput::new(index_crate, false);
+        output.set_rust_version(None).unwrap();
 
         assert_eq!(output.to_string(), expected);
     }
 
     #[test]
     fn test_set_rust_version_output_with_minimum_rust() {
-        let expected = "\n  Crate versions for \u{1b}[38;5;6mforestry\u{1b}[0m.\n  🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶\n    Minimum Rust version: not specified (\u{1b}[38;5;3m (WARNING: Some dependencies do not specify a Rust version)\u{1b}[0m)\n";
+        let expected = "\n  Crate versions for \u{1b}[38;5;6mforestry\u{1b}[0m.\n  🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶\n    Minimum Rust version: not specified (\u{1b}[38;5;3mWARNING: Some dependencies do not specify a Rust version\u{1b}[0m)\n    Crates inspected: 12\n";
 
         let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
         let lock = FileLock::unlocked();
@@ -266,16 +900,17 @@ mod tests {
             .unwrap()
             .unwrap();
 
-        let mut output = RustVersionOutput::new(index_crate);
+        let report = report(&index, &index_crate, WalkOptions::default()).unwrap();
+        let mut output = RustVersionOutput::new(index_crate, false);
 
-        output.set_minimum_rust_version_required(&index).unwrap();
+        output.set_minimum_rust_version(&report);
 
         assert_eq!(output.to_string(), expected);
     }
 
     #[test]
     fn test_set_rust_version_output_with_specified_version_and_minimum_rust() {
-        let expected = "\n  Crate versions for \u{1b}[38;5;6mforestry\u{1b}[0m.\n  🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶\n    Most recent version: 1.4.1 (Rust version: \u{1b}[38;5;4;1mnot specified\u{1b}[0m)\n    Minimum Rust version: not specified (\u{1b}[38;5;3m (WARNING: Some dependencies do not specify a Rust version)\u{1b}[0m)\n";
+        let expected = "\n  Crate versions for \u{1b}[38;5;6mforestry\u{1b}[0m.\n  🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶🭶\n    Most recent version: 1.4.1 (Rust version: \u{1b}[38;5;4;1mnot specified\u{1b}[0m)\n    Minimum Rust version: not specified (\u{1b}[38;5;3mWARNING: Some dependencies do not specify a Rust version\u{1b}[0m)\n    Crates inspected: 12\n";
 
         let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
         let lock = FileLock::unlocked();
@@ -285,10 +920,569 @@ mod tests {
             .unwrap()
             .unwrap();
 
-        let mut output = RustVersionOutput::new(index_crate);
-        output.set_rust_version().unwrap();
-        output.set_minimum_rust_version_required(&index).unwrap();
+        let report = report(&index, &index_crate, WalkOptions::default()).unwrap();
+        let mut output = RustVersionOutput::new(index_crate, false);
+        output.set_rust_version(None).unwrap();
+        output.set_minimum_rust_version(&report);
 
         assert_eq!(output.to_string(), expected);
     }
+
+    fn report(
+        index: &ComboIndex,
+        index_crate: &IndexKrate,
+        options: WalkOptions,
+    ) -> Result<RustVersionReport, Error> {
+        let mut walk = RustVersionWalk::new(index, options);
+        let version = index_crate.most_recent_version();
+        let dependencies = walk.walk(index_crate, version)?;
+        RustVersionReport::new(version, dependencies, &walk)
+    }
+
+    fn krate(index: &ComboIndex, name: &str) -> IndexKrate {
+        let lock = FileLock::unlocked();
+        index
+            .krate(KrateName::crates_io(name).unwrap(), true, &lock)
+            .unwrap()
+            .unwrap()
+    }
+
+    /// Rewrites the index entry of a crate in the local registry
+    fn write_entry(registry: &str, path: &str, entry: &str) {
+        let path = std::path::Path::new(registry).join("index").join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, entry).unwrap();
+    }
+
+    #[test]
+    fn test_rust_version_report() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        let index = crate::tests::get_test_index(&registry).unwrap();
+        let index_crate = krate(&index, "forestry");
+
+        let report = report(&index, &index_crate, WalkOptions::default()).unwrap();
+
+        assert_eq!(
+            report,
+            RustVersionReport {
+                name: "forestry".to_string(),
+                version: "1.4.1".to_string(),
+                rust_version: None,
+                minimum_rust_version: None,
+                crates_inspected: 12,
+                crates_without_rust_version: 11,
+                constrained_by: vec![],
+                dependencies: vec![DependencyRustVersion {
+                    name: "colored".to_string(),
+                    req: "^2.1.0".to_string(),
+                    kind: DependencyKind::Normal,
+                    version: Some("2.1.0".to_string()),
+                    rust_version: None,
+                    minimum_rust_version: None,
+                }],
+                crates: report.crates.clone(),
+            }
+        );
+        assert_eq!(report.crates.len(), 12);
+        assert_eq!(
+            report.crates[0],
+            CrateRustVersion {
+                name: "colored".to_string(),
+                version: "2.1.0".to_string(),
+                rust_version: None,
+                minimum_rust_version: None,
+            }
+        );
+        assert_eq!(report.records().len(), 1);
+    }
+
+    #[test]
+    fn test_walk_finds_deep_rust_version() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        let entry = std::fs::read_to_string(format!("{registry}/index/wi/nd/windows_x86_64_msvc"))
+            .unwrap()
+            .replace(
+                r#""yanked":false"#,
+                r#""yanked":false,"rust_version":"1.70""#,
+            );
+        write_entry(&registry, "wi/nd/windows_x86_64_msvc", &entry);
+        let index = crate::tests::get_test_index(&registry).unwrap();
+        let index_crate = krate(&index, "forestry");
+
+        let report = report(&index, &index_crate, WalkOptions::default()).unwrap();
+
+        assert_eq!(
+            report.minimum_rust_version,
+            Some(RustVersion::new(1, 70, None))
+        );
+        assert_eq!(
+            report.dependencies[0].minimum_rust_version,
+            Some(RustVersion::new(1, 70, None))
+        );
+        assert_eq!(report.dependencies[0].rust_version, None);
+        assert_eq!(report.crates_without_rust_version, 10);
+        let chain = report
+            .constrained_by
+            .iter()
+            .map(|link| format!("{} {}", link.name, link.version))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            chain,
+            [
+                "forestry 1.4.1",
+                "colored 2.1.0",
+                "windows-sys 0.48.0",
+                "windows-targets 0.48.5",
+                "windows_x86_64_msvc 0.48.5",
+            ]
+        );
+
+        let mut output = RustVersionOutput::new(index_crate, false);
+        output.set_minimum_rust_version(&report);
+        assert!(output.to_string().contains(
+            "\n    Set by: forestry 1.4.1 -> colored 2.1.0 -> windows-sys 0.48.0 -> windows-targets 0.48.5 -> windows_x86_64_msvc 0.48.5 (rust-version 1.70)\n"
+        ));
+    }
+
+    #[test]
+    fn test_walk_tree() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        let index = crate::tests::get_test_index(&registry).unwrap();
+        let index_crate = krate(&index, "forestry");
+        let mut walk = RustVersionWalk::new(&index, WalkOptions::default());
+        walk.walk(&index_crate, index_crate.most_recent_version())
+            .unwrap();
+
+        let tree = walk.tree(index_crate.most_recent_version());
+
+        assert_eq!(tree.len(), 12);
+        assert_eq!(tree[0], "forestry 1.4.1 (rust-version not specified)");
+        assert_eq!(tree[1], "└── colored 2.1.0 (rust-version not specified)");
+        assert_eq!(
+            tree[2],
+            "    ├── lazy_static 1.5.0 (rust-version not specified)"
+        );
+        assert_eq!(
+            tree[11],
+            "            └── windows_x86_64_msvc 0.48.5 (rust-version not specified)"
+        );
+    }
+
+    #[test]
+    fn test_walk_follows_default_features() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        let index = crate::tests::get_test_index(&registry).unwrap();
+        let index_crate = krate(&index, "holochain_serialized_bytes_derive");
+        let version = index_crate.most_recent_version();
+        let mut walk = RustVersionWalk::new(&index, WalkOptions::default());
+        walk.walk(&index_crate, version).unwrap();
+
+        // syn depends on quote through its default `printing` feature
+        let tree = walk.tree(version);
+        assert_eq!(
+            tree[5],
+            "    ├── proc-macro2 1.0.89 (rust-version not specified) (*)"
+        );
+        assert_eq!(
+            tree[6],
+            "    ├── quote 1.0.37 (rust-version not specified) (*)"
+        );
+
+        let mut closure = DependencyClosure::new(&index, WalkOptions::default());
+        closure.walk(&index_crate, &[version]).unwrap();
+        let walked = walk
+            .crates()
+            .into_iter()
+            .filter(|krate| krate.name != index_crate.name())
+            .map(|krate| format!("{} {}", krate.name, krate.version))
+            .collect::<BTreeSet<_>>();
+        let closure = closure
+            .dependencies()
+            .flat_map(|(krate, versions)| {
+                versions
+                    .into_iter()
+                    .map(|version| format!("{} {}", krate.name(), version.version))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<BTreeSet<_>>();
+        assert_eq!(walked, closure);
+    }
+
+    #[test]
+    fn test_walk_stops_at_cycles() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        let entry = |name: &str, dependency: &str, rust_version: &str| {
+            format!(
+                r#"{{"name":"{name}","vers":"1.0.0","deps":[{{"name":"{dependency}","req":"^1","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal"}}],"cksum":"0000000000000000000000000000000000000000000000000000000000000000","features":{{}},"yanked":false,"rust_version":"{rust_version}"}}"#
+            )
+        };
+        write_entry(&registry, "1/a", &entry("a", "b", "1.60"));
+        write_entry(&registry, "1/b", &entry("b", "c", "1.70"));
+        write_entry(&registry, "1/c", &entry("c", "a", "1.9"));
+        let index = crate::tests::get_test_index(&registry).unwrap();
+        let index_crate = krate(&index, "a");
+
+        let report = report(&index, &index_crate, WalkOptions::default()).unwrap();
+
+        assert_eq!(
+            report.minimum_rust_version,
+            Some(RustVersion::new(1, 70, None))
+        );
+        assert_eq!(report.crates_inspected, 3);
+        assert_eq!(report.crates_without_rust_version, 0);
+        assert_eq!(
+            report
+                .constrained_by
+                .iter()
+                .map(|link| link.name.as_str())
+                .collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+
+        let mut walk = RustVersionWalk::new(&index, WalkOptions::default());
+        walk.walk(&index_crate, index_crate.most_recent_version())
+            .unwrap();
+        assert_eq!(
+            walk.tree(index_crate.most_recent_version()),
+            [
+                "a 1.0.0 (rust-version 1.60)",
+                "└── b 1.0.0 (rust-version 1.70)",
+                "    └── c 1.0.0 (rust-version 1.9)",
+                "        └── a 1.0.0 (rust-version 1.60) (*)",
+            ]
+        );
+    }
+
+    /// Index entries for a crate with versions 1.0.0, 1.1.0, a yanked 1.2.0
+    /// and 2.0.0, declaring rising Rust versions, and a crate depending on
+    /// `^1` of it
+    fn write_versioned_entries(registry: &str) {
+        let version = |version: &str, yanked: bool, rust_version: &str| {
+            format!(
+                r#"{{"name":"dep","vers":"{version}","deps":[],"cksum":"0000000000000000000000000000000000000000000000000000000000000000","features":{{}},"yanked":{yanked},"rust_version":"{rust_version}"}}"#
+            )
+        };
+        let entries = [
+            version("1.1.0", false, "1.60"),
+            version("1.0.0", false, "1.56"),
+            version("1.2.0", true, "1.80"),
+            version("2.0.0", false, "1.85"),
+        ];
+        write_entry(registry, "3/d/dep", &entries.join("\n"));
+        write_entry(
+            registry,
+            "3/t/top",
+            r#"{"name":"top","vers":"0.1.0","deps":[{"name":"dep","req":"^1","features":[],"optional":false,"default_features":true,"target":null,"kind":"normal"}],"cksum":"0000000000000000000000000000000000000000000000000000000000000000","features":{},"yanked":false}"#,
+        );
+    }
+
+    #[test]
+    fn test_walk_resolves_highest_matching_version() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        write_versioned_entries(&registry);
+        let index = crate::tests::get_test_index(&registry).unwrap();
+        let index_crate = krate(&index, "top");
+
+        let report = report(&index, &index_crate, WalkOptions::default()).unwrap();
+
+        assert_eq!(report.dependencies[0].version.as_deref(), Some("1.1.0"));
+        assert_eq!(
+            report.minimum_rust_version,
+            Some(RustVersion::new(1, 60, None))
+        );
+    }
+
+    #[test]
+    fn test_walk_resolves_minimal_versions() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        write_versioned_entries(&registry);
+        let index = crate::tests::get_test_index(&registry).unwrap();
+        let index_crate = krate(&index, "top");
+        let options = WalkOptions {
+            minimal_versions: true,
+            ..WalkOptions::default()
+        };
+
+        let report = report(&index, &index_crate, options).unwrap();
+
+        assert_eq!(report.dependencies[0].version.as_deref(), Some("1.0.0"));
+        assert_eq!(
+            report.minimum_rust_version,
+            Some(RustVersion::new(1, 56, None))
+        );
+    }
+
+    #[test]
+    fn test_matching_version_skips_yanked() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        write_versioned_entries(&registry);
+        let index = crate::tests::get_test_index(&registry).unwrap();
+        let index_crate = krate(&index, "dep");
+        let matching = |req: &str, minimal_versions| {
+            matching_version(
+                &index_crate,
+                &VersionReq::parse(req).unwrap(),
+                minimal_versions,
+            )
+            .unwrap()
+            .map(|version| version.version.to_string())
+        };
+
+        assert_eq!(matching("^1.2", false), None);
+        assert_eq!(matching(">=1.1", false).as_deref(), Some("2.0.0"));
+        assert_eq!(matching(">=1.1", true).as_deref(), Some("1.1.0"));
+    }
+
+    #[test]
+    fn test_walk_invalid_rust_version() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        let entry = std::fs::read_to_string(format!("{registry}/index/co/lo/colored"))
+            .unwrap()
+            .replace(
+                r#""yanked":false"#,
+                r#""yanked":false,"rust_version":"1.70-nightly""#,
+            );
+        write_entry(&registry, "co/lo/colored", &entry);
+        let index = crate::tests::get_test_index(&registry).unwrap();
+        let index_crate = krate(&index, "forestry");
+
+        let result = report(&index, &index_crate, WalkOptions::default());
+
+        assert!(
+            matches!(result, Err(Error::InvalidRustVersion(value)) if value == "1.70-nightly (declared by colored 2.1.0)")
+        );
+    }
+
+    #[test]
+    fn test_walk_optional_dependencies() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        let index = crate::tests::get_test_index(&registry).unwrap();
+        let index_crate = krate(&index, "forestry");
+        let options = WalkOptions {
+            optional: true,
+            ..WalkOptions::default()
+        };
+
+        // lazy_static optionally depends on spin, which is not in the registry
+        let result = report(&index, &index_crate, options);
+
+        assert!(matches!(result, Err(Error::CrateNotFoundOnIndex)));
+    }
+
+    #[test]
+    fn test_walk_followed_kinds() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        let index = crate::tests::get_test_index(&registry).unwrap();
+        let index_crate = krate(&index, "lazy_static");
+        let version = index_crate.most_recent_version();
+        let followed = |options, root| {
+            RustVersionWalk::new(&index, options)
+                .followed(version, root)
+                .map(|dep| dep.crate_name().to_string())
+                .collect::<Vec<_>>()
+        };
+        let dev = WalkOptions {
+            dev: true,
+            ..WalkOptions::default()
+        };
+
+        assert!(followed(WalkOptions::default(), true).is_empty());
+        assert_eq!(followed(dev.clone(), true), vec!["doc-comment", "trybuild"]);
+        assert!(followed(dev, false).is_empty());
+    }
+
+    #[test]
+    fn test_parse_walk_options() {
+        let rust_versions = RustVersions::parse_from([
+            "program",
+            "--optional",
+            "--dev",
+            "--no-build",
+            "--minimal-versions",
+            "forestry",
+        ]);
+        assert_eq!(
+            rust_versions.walk_options(),
+            WalkOptions {
+                optional: true,
+                dev: true,
+                build: false,
+                minimal_versions: true,
+                ..WalkOptions::default()
+            }
+        );
+        assert_eq!(
+            RustVersions::parse_from(["program", "forestry"]).walk_options(),
+            WalkOptions::default()
+        );
+    }
+
+    #[test]
+    fn test_parse_version_and_req() {
+        let rust_versions =
+            RustVersions::try_parse_from(["program", "--version", "1.2.3", "forestry"]).unwrap();
+        assert_eq!(rust_versions.version.as_deref(), Some("1.2.3"));
+
+        let rust_versions =
+            RustVersions::try_parse_from(["program", "--req", "^1.2", "forestry"]).unwrap();
+        assert_eq!(rust_versions.req, Some(VersionReq::parse("^1.2").unwrap()));
+
+        let result = RustVersions::try_parse_from([
+            "program",
+            "--version",
+            "1.2.3",
+            "--req",
+            "^1.2",
+            "forestry",
+        ]);
+        assert!(result.is_err());
+        let result = RustVersions::try_parse_from(["program", "--req", "not a req", "forestry"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_select_version() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        write_versioned_entries(&registry);
+        let index = crate::tests::get_test_index(&registry).unwrap();
+        let index_crate = krate(&index, "dep");
+        let select = |args: &[&str]| {
+            let args = ["program"].iter().chain(args).chain(&["dep"]);
+            RustVersions::parse_from(args)
+                .select_version(&index, &index_crate)
+                .map(|version| version.version.to_string())
+        };
+
+        assert_eq!(select(&[]).unwrap(), "2.0.0");
+        assert_eq!(select(&["--version", "1.2.0"]).unwrap(), "1.2.0");
+        assert_eq!(select(&["--req", "^1"]).unwrap(), "1.1.0");
+        assert_eq!(select(&["--req", "<1.1"]).unwrap(), "1.0.0");
+        assert!(matches!(
+            select(&["--version", "9.9.9"]),
+            Err(Error::VersionNotFound(_, _))
+        ));
+        let error = select(&["--req", "^3"]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "No version of dep matches: requirement ^3"
+        );
+    }
+
+    #[test]
+    fn test_select_toolchain_version() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        write_versioned_entries(&registry);
+        let index = crate::tests::get_test_index(&registry).unwrap();
+        let index_crate = krate(&index, "dep");
+        let select = |toolchain: &str| {
+            RustVersions::parse_from(["program", "--toolchain", toolchain, "dep"])
+                .select_version(&index, &index_crate)
+                .map(|version| version.version.to_string())
+        };
+
+        assert_eq!(select("1.90").unwrap(), "2.0.0");
+        assert_eq!(select("1.70").unwrap(), "1.1.0");
+        assert_eq!(select("1.56.0").unwrap(), "1.0.0");
+        assert_eq!(
+            select("1.50").unwrap_err().to_string(),
+            "No version of dep matches: Rust version 1.50 or lower"
+        );
+    }
+
+    #[test]
+    fn test_select_toolchain_version_with_dependencies() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        write_versioned_entries(&registry);
+        let top = std::fs::read_to_string(format!("{registry}/index/3/t/top")).unwrap();
+        let newer = top
+            .replace(r#""vers":"0.1.0""#, r#""vers":"0.2.0""#)
+            .replace(r#""req":"^1""#, r#""req":"^2""#)
+            .replace(
+                r#""yanked":false"#,
+                r#""yanked":false,"rust_version":"1.56""#,
+            );
+        write_entry(&registry, "3/t/top", &format!("{top}\n{newer}"));
+        let index = crate::tests::get_test_index(&registry).unwrap();
+        let index_crate = krate(&index, "top");
+        let select = |args: &[&str]| {
+            let args = ["program"].iter().chain(args).chain(&["top"]);
+            RustVersions::parse_from(args)
+                .select_version(&index, &index_crate)
+                .map(|version| version.version.to_string())
+        };
+
+        assert_eq!(select(&["--toolchain", "1.70"]).unwrap(), "0.2.0");
+        assert_eq!(
+            select(&["--toolchain", "1.70", "--with-dependencies"]).unwrap(),
+            "0.1.0"
+        );
+        assert_eq!(
+            select(&["--toolchain", "1.58", "--with-dependencies"])
+                .unwrap_err()
+                .to_string(),
+            "No version of top matches: Rust version 1.58 or lower, including its dependencies"
+        );
+    }
+
+    #[test]
+    fn test_parse_toolchain() {
+        let rust_versions =
+            RustVersions::try_parse_from(["program", "--toolchain", "1.70", "forestry"]).unwrap();
+        assert_eq!(rust_versions.toolchain, Some(RustVersion::new(1, 70, None)));
+        assert_eq!(
+            rust_versions.selection_label().as_deref(),
+            Some("Highest version for Rust 1.70")
+        );
+
+        for args in [
+            ["program", "--toolchain", "1.x", "forestry"].as_slice(),
+            &["program", "--with-dependencies", "forestry"],
+            &["program", "--toolchain", "1.70", "--req", "^1", "forestry"],
+            &[
+                "program",
+                "--toolchain",
+                "1.70",
+                "--version",
+                "1.0.0",
+                "forestry",
+            ],
+        ] {
+            assert!(RustVersions::try_parse_from(args).is_err(), "{args:?}");
+        }
+    }
+
+    #[test]
+    fn test_run_version() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        write_versioned_entries(&registry);
+        let options = IndexOptions::new(crate::Registry::Local(registry.into()));
+        let rust_versions =
+            RustVersions::parse_from(["program", "--version", "1.0.0", "--tree", "dep"]);
+
+        let output = rust_versions
+            .run(false, &options, OutputFormat::Text)
+            .unwrap();
+
+        assert!(output.contains("    Version: 1.0.0 (Rust version: "));
+        assert!(output.contains("    Minimum Rust version: 1.56\n"));
+        assert!(output.contains("      dep 1.0.0 (rust-version 1.56)\n"));
+    }
+
+    #[test]
+    fn test_run_no_colour() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        write_versioned_entries(&registry);
+        let options = IndexOptions::new(crate::Registry::Local(registry.into()));
+
+        for args in [vec!["program", "dep"], vec!["program", "--history", "dep"]] {
+            let output = RustVersions::parse_from(args)
+                .run(true, &options, OutputFormat::Text)
+                .unwrap();
+
+            assert!(
+                !output.contains('\u{1b}'),
+                "Expected no escapes in {output}"
+            );
+        }
+    }
 }
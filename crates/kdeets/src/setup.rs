@@ -1,12 +1,21 @@
-use crate::Error;
+use std::collections::BTreeMap;
+use std::path::Path;
 
-use clap::{Parser, ValueEnum};
+use crate::client;
+use crate::walk::{DependencyClosure, WalkOptions, matching_versions};
+use crate::{ComboIndex, Error, IndexOptions, OutputFormat};
+
+use clap::{Parser, Subcommand, ValueEnum};
 use clap_verbosity::Verbosity;
 use disksize::DiskSize;
-use output::SetupTestOutputBuilder;
-use tame_index::{KrateName, index::FileLock};
+use lockfile::LockedPackage;
+use output::{SetupTestOutputBuilder, with_versions};
+use semver::VersionReq;
+use tame_index::{IndexKrate, IndexVersion, KrateName, index::FileLock};
 
 mod disksize;
+mod lockfile;
+mod manifest;
 mod output;
 
 #[derive(Debug, Parser, Default, ValueEnum, Clone)]
@@ -19,94 +28,295 @@ enum SelectVersion {
     None,
 }
 
-#[derive(Parser, Debug, Default)]
-#[clap(author, version, about, long_about = None)]
+#[derive(Parser, Debug, Default, Clone)]
+#[clap(author, about, long_about = None)]
 pub struct Setup {
     #[clap(flatten)]
     logging: Verbosity,
     /// Do not replace the existing registry if it exists
     #[clap(default_value = "false", short = 'r', long)]
     no_replace: bool,
+    /// Add to the existing registry, downloading only the crate versions it does not hold
+    #[clap(long, conflicts_with = "no_replace")]
+    append: bool,
     #[clap(
         short,
         long,
-        help = "Add dependencies based on specific version\n",
+        help = "Add dependencies based on specific version, which is also the version added when neither `--version` nor `--req` is given\n",
         default_value = "latest"
     )]
     dependencies: SelectVersion,
+    /// Add this version of the crate
+    #[clap(long = "version", value_name = "VERSION", conflicts_with = "req")]
+    version: Option<String>,
+    /// Add every version of the crate, that is not yanked, matching the semver requirement
+    #[clap(long = "req", value_name = "REQ")]
+    req: Option<VersionReq>,
+    /// Add every published version of the crate and of its dependencies, not only the selected versions
+    #[clap(long = "all-versions")]
+    all_versions: bool,
+    /// Include every optional dependency, not only those enabled by default or requested features
+    #[clap(long = "optional")]
+    optional: bool,
+    /// Include the dev dependencies of the crate, used by its tests, examples and benchmarks
+    #[clap(long = "dev")]
+    dev: bool,
+    /// Exclude build dependencies, used by build scripts
+    #[clap(long = "no-build")]
+    no_build: bool,
+    /// Exclude dependencies for the target: a target triple, a `cfg(...)` predicate, or the predicate inside it such as `windows`
+    #[clap(long = "exclude-target", value_name = "TARGET")]
+    exclude_targets: Vec<String>,
     /// The location for the local registry
     #[clap(short, long, default_value = "tests/local_registry")]
     location: String,
+    /// Add every registry package locked in the lockfile, at its locked version, instead of a crate
+    #[clap(
+        long = "from-lock",
+        value_name = "LOCKFILE",
+        conflicts_with_all = ["crate_", "manifest", "version", "req", "dependencies", "all_versions", "optional", "dev", "no_build", "exclude_targets"]
+    )]
+    from_lock: Option<String>,
+    /// Add the crates needed by the dependencies of the manifest, and of its workspace members, instead of a crate
+    #[clap(
+        long = "manifest",
+        value_name = "MANIFEST",
+        conflicts_with_all = ["crate_", "version", "req", "dependencies", "dev"]
+    )]
+    manifest: Option<String>,
     /// The name of the crate
-    crate_: String,
+    #[clap(required_unless_present_any = ["from_lock", "manifest"])]
+    crate_: Option<String>,
+}
+
+/// Manage a local registry written by `setup`
+#[derive(Parser, Debug)]
+#[clap(author, about, long_about = None)]
+pub struct RegistryCommand {
+    #[command(subcommand)]
+    command: RegistrySubcommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum RegistrySubcommand {
+    /// Add a crate, or the crates of a lockfile or manifest, to an existing local registry
+    Add(Setup),
+}
+
+impl RegistryCommand {
+    pub fn run(&self, options: &IndexOptions, format: OutputFormat) -> Result<String, Error> {
+        match &self.command {
+            RegistrySubcommand::Add(setup) => {
+                let setup = Setup {
+                    append: true,
+                    ..setup.clone()
+                };
+                setup.run(options, format)
+            }
+        }
+    }
 }
 
 impl Setup {
-    pub fn run(&self) -> Result<String, Error> {
-        log::info!(
-            "Setting up local registry and adding crate: {}",
-            self.crate_
-        );
+    pub fn run(&self, options: &IndexOptions, format: OutputFormat) -> Result<String, Error> {
+        if let Some(lockfile) = &self.from_lock {
+            let packages = lockfile::registry_packages(Path::new(lockfile), options.registry())?;
+            return self.run_from_lock(lockfile, &packages, &options.combo_index()?, format);
+        }
+        if let Some(manifest) = &self.manifest {
+            return self.run_from_manifest(manifest, options, format);
+        }
+
+        let crate_ = self.crate_.as_deref().unwrap_or_default();
+        log::info!("Setting up local registry and adding crate: {crate_}");
         let lock = FileLock::unlocked();
 
-        let combo_index = crate::get_remote_combo_index()?;
-        let crate_name = KrateName::crates_io(&self.crate_)?;
+        let combo_index = options.combo_index()?;
+        let crate_name = KrateName::cargo(crate_)?;
 
         let index_crate = combo_index.krate(crate_name, true, &lock)?;
 
         let Some(index_crate) = index_crate else {
-            return Err(Error::CrateNotFoundOnIndex);
+            return Err(combo_index.not_found(crate_));
         };
 
-        let registry = if self.location.is_empty() {
+        let registry = self.registry();
+        log::debug!("Creating registry at {registry}");
+        let mut output = SetupTestOutputBuilder::new(index_crate.clone(), registry);
+
+        let selected = self.select_versions(&index_crate)?;
+
+        self.open_registry(&mut output)?;
+
+        if self.all_versions {
+            output.insert_crate(&index_crate, &combo_index)?;
+        } else {
+            output.insert_crate(&with_versions(&index_crate, &selected), &combo_index)?;
+        }
+
+        if matches!(self.dependencies, SelectVersion::None) {
+            log::debug!("Not adding dependencies");
+        } else {
+            output.add_dependency_crates(
+                &index_crate,
+                &selected,
+                &combo_index,
+                self.walk_options(),
+            )?;
+        }
+        log::debug!("Finalizing registry");
+        let final_output = output.finalize()?;
+        log::debug!("Registry setup complete");
+        format.render(&final_output, || Ok(final_output.to_string()))
+    }
+
+    /// Sets up the registry with exactly the registry packages locked in the
+    /// lockfile, verifying each against the checksum in the lockfile
+    fn run_from_lock(
+        &self,
+        lockfile: &str,
+        packages: &BTreeMap<String, Vec<LockedPackage>>,
+        combo_index: &ComboIndex,
+        format: OutputFormat,
+    ) -> Result<String, Error> {
+        log::info!("Setting up local registry from lockfile: {lockfile}");
+        let lock = FileLock::unlocked();
+
+        let mut locked_crates = vec![];
+        for (name, locked) in packages {
+            let Some(index_crate) = combo_index.krate(KrateName::cargo(name)?, true, &lock)? else {
+                return Err(combo_index.not_found(name));
+            };
+            let mut versions = vec![];
+            for package in locked {
+                versions.push(package.find_in(&index_crate)?);
+            }
+            locked_crates.push(with_versions(&index_crate, &versions));
+        }
+
+        let registry = self.registry();
+        log::debug!("Creating registry at {registry}");
+        let mut output = SetupTestOutputBuilder::for_file(lockfile, registry);
+        self.open_registry(&mut output)?;
+
+        for index_crate in &locked_crates {
+            // The download is validated against the index checksum, which
+            // matches the lockfile
+            output.insert_crate(index_crate, combo_index)?;
+        }
+
+        log::debug!("Finalizing registry");
+        let final_output = output.finalize()?;
+        log::debug!("Registry setup complete");
+        format.render(&final_output, || Ok(final_output.to_string()))
+    }
+
+    /// Sets up the registry with the dependency graph of every registry
+    /// dependency declared by the manifest and its workspace members
+    fn run_from_manifest(
+        &self,
+        manifest: &str,
+        options: &IndexOptions,
+        format: OutputFormat,
+    ) -> Result<String, Error> {
+        log::info!("Setting up local registry from manifest: {manifest}");
+        let walk_options = self.walk_options();
+        let registry = options.registry().name()?;
+        let requirements =
+            manifest::requirements(Path::new(manifest), &walk_options, registry.as_deref())?;
+        let combo_index = options.combo_index()?;
+
+        let mut closure = DependencyClosure::new(&combo_index, walk_options);
+        closure.walk_requirements(&requirements)?;
+
+        let registry = self.registry();
+        log::debug!("Creating registry at {registry}");
+        let mut output = SetupTestOutputBuilder::for_file(manifest, registry);
+        self.open_registry(&mut output)?;
+        output.insert_closure(&closure, &combo_index)?;
+
+        log::debug!("Finalizing registry");
+        let final_output = output.finalize()?;
+        log::debug!("Registry setup complete");
+        format.render(&final_output, || Ok(final_output.to_string()))
+    }
+
+    /// Opens the existing registry when appending, or else creates it,
+    /// replacing any registry already there unless told not to
+    fn open_registry(&self, output: &mut SetupTestOutputBuilder) -> Result<(), Error> {
+        if self.append {
+            output.open_local_registry()?;
+        } else {
+            output.initialise_local_registry(self.no_replace)?;
+        }
+        Ok(())
+    }
+
+    fn registry(&self) -> &str {
+        if self.location.is_empty() {
             "tests/local_registry"
         } else {
             &self.location
-        };
-        log::debug!("Creating registry at {registry}");
-        let mut output = SetupTestOutputBuilder::new(index_crate.clone(), registry);
+        }
+    }
 
-        output.initialise_local_registry(self.no_replace)?;
+    fn walk_options(&self) -> WalkOptions {
+        WalkOptions {
+            optional: self.optional,
+            dev: self.dev,
+            build: !self.no_build,
+            exclude_targets: self.exclude_targets.clone(),
+            all_versions: self.all_versions,
+            ..WalkOptions::default()
+        }
+    }
 
-        output.insert_crate(&index_crate)?;
+    /// The versions of the crate to add: the version given, the versions
+    /// matching the requirement, or else the version whose dependencies are
+    /// added
+    fn select_versions<'k>(
+        &self,
+        index_crate: &'k IndexKrate,
+    ) -> Result<Vec<&'k IndexVersion>, Error> {
+        if let Some(version) = &self.version {
+            return Ok(vec![client::select_version(index_crate, Some(version))?]);
+        }
 
-        match self.dependencies {
-            SelectVersion::Latest => {
-                log::debug!("Adding dependencies for most recent version");
-                output.add_dependency_crates(
-                    index_crate.most_recent_version().dependencies(),
-                    &combo_index,
-                )?;
+        if let Some(req) = &self.req {
+            let matching = matching_versions(index_crate, req)?;
+            if matching.is_empty() {
+                return Err(Error::NoMatchingVersion(
+                    index_crate.name().to_string(),
+                    format!("requirement {req}"),
+                ));
+            }
+            return Ok(matching);
+        }
+
+        let index_version = match self.dependencies {
+            SelectVersion::Latest | SelectVersion::None => {
+                log::debug!("Selecting most recent version");
+                index_crate.most_recent_version()
             }
             SelectVersion::Earliest => {
-                log::debug!("Adding dependencies for earliest version");
-                output.add_dependency_crates(
-                    index_crate.earliest_version().dependencies(),
-                    &combo_index,
-                )?;
+                log::debug!("Selecting earliest version");
+                index_crate.earliest_version()
             }
             SelectVersion::Highest => {
-                log::debug!("Adding dependencies for highest version");
-                output.add_dependency_crates(
-                    index_crate.highest_version().dependencies(),
-                    &combo_index,
-                )?;
+                log::debug!("Selecting highest version");
+                index_crate.highest_version()
             }
             SelectVersion::HighestNormal => {
-                log::debug!("Attempting to add dependencies for highest normal version");
-                let opt_index_version = index_crate.highest_normal_version();
-                if let Some(index_version) = opt_index_version {
-                    output.add_dependency_crates(index_version.dependencies(), &combo_index)?;
-                } else {
-                    log::warn!("No normal version found for crate: {}", self.crate_);
-                }
+                log::debug!("Selecting highest normal version");
+                index_crate.highest_normal_version().ok_or_else(|| {
+                    Error::NoMatchingVersion(
+                        index_crate.name().to_string(),
+                        "not yanked or a pre-release".to_string(),
+                    )
+                })?
             }
-            SelectVersion::None => (),
-        }
-        log::debug!("Finalizing registry");
-        let final_output = output.finalize()?;
-        log::debug!("Registry setup complete");
-        Ok(final_output.to_string())
+        };
+        Ok(vec![index_version])
     }
 }
 
@@ -124,11 +334,11 @@ mod tests {
         let _log = simple_logger::init_with_level(log::Level::Debug);
 
         let setup = Setup {
-            crate_: String::from(TEST_CRATE_NAME),
+            crate_: Some(String::from(TEST_CRATE_NAME)),
             dependencies: SelectVersion::Latest,
             ..Default::default()
         };
-        let result = setup.run();
+        let result = setup.run(&IndexOptions::default(), OutputFormat::Text);
         log::debug!("Result: {result:?}");
         assert!(result.is_ok());
         assert!(Path::new("tests/local_registry").exists());
@@ -141,12 +351,12 @@ mod tests {
         let location = temp_dir.path().to_str().unwrap();
 
         let setup = Setup {
-            crate_: String::from(TEST_CRATE_NAME),
+            crate_: Some(String::from(TEST_CRATE_NAME)),
             dependencies: SelectVersion::Latest,
             location: location.to_string(),
             ..Default::default()
         };
-        let result = setup.run();
+        let result = setup.run(&IndexOptions::default(), OutputFormat::Text);
         log::debug!("Result: {result:?}");
         assert!(result.is_ok());
         assert!(Path::new(location).exists());
@@ -158,12 +368,12 @@ mod tests {
         let temp_dir = tempfile::tempdir().unwrap();
         let location = temp_dir.path().to_str().unwrap();
         let setup = Setup {
-            crate_: String::from(TEST_CRATE_NAME),
+            crate_: Some(String::from(TEST_CRATE_NAME)),
             dependencies: SelectVersion::Earliest,
             location: location.to_string(),
             ..Default::default()
         };
-        let result = setup.run();
+        let result = setup.run(&IndexOptions::default(), OutputFormat::Text);
         log::debug!("Result: {result:?}");
         assert!(result.is_ok());
     }
@@ -174,12 +384,12 @@ mod tests {
         let temp_dir = tempfile::tempdir().unwrap();
         let location = temp_dir.path().to_str().unwrap();
         let setup = Setup {
-            crate_: String::from(TEST_CRATE_NAME),
+            crate_: Some(String::from(TEST_CRATE_NAME)),
             dependencies: SelectVersion::HighestNormal,
             location: location.to_string(),
             ..Default::default()
         };
-        let result = setup.run();
+        let result = setup.run(&IndexOptions::default(), OutputFormat::Text);
         log::debug!("Result: {result:?}");
         assert!(result.is_ok());
     }
@@ -190,12 +400,12 @@ mod tests {
         let temp_dir = tempfile::tempdir().unwrap();
         let location = temp_dir.path().to_str().unwrap();
         let setup = Setup {
-            crate_: String::from(TEST_CRATE_NAME),
+            crate_: Some(String::from(TEST_CRATE_NAME)),
             dependencies: SelectVersion::Highest,
             location: location.to_string(),
             ..Default::default()
         };
-        let result = setup.run();
+        let result = setup.run(&IndexOptions::default(), OutputFormat::Text);
         log::debug!("Result: {result:?}");
         assert!(result.is_ok());
     }
@@ -206,12 +416,12 @@ mod tests {
         let temp_dir = tempfile::tempdir().unwrap();
         let location = temp_dir.path().to_str().unwrap();
         let setup = Setup {
-            crate_: String::from(TEST_CRATE_NAME),
+            crate_: Some(String::from(TEST_CRATE_NAME)),
             dependencies: SelectVersion::None,
             location: location.to_string(),
             ..Default::default()
         };
-        let result = setup.run();
+        let result = setup.run(&IndexOptions::default(), OutputFormat::Text);
         log::debug!("Result: {result:?}");
         assert!(result.is_ok());
     }
@@ -222,12 +432,12 @@ mod tests {
         let temp_dir = tempfile::tempdir().unwrap();
         let location = temp_dir.path().to_str().unwrap();
         let setup = Setup {
-            crate_: String::from(TEST_NON_EXISTENT_CRATE_NAME),
+            crate_: Some(String::from(TEST_NON_EXISTENT_CRATE_NAME)),
             dependencies: SelectVersion::Latest,
             location: location.to_string(),
             ..Default::default()
         };
-        let result = setup.run();
+        let result = setup.run(&IndexOptions::default(), OutputFormat::Text);
         log::debug!("Result: {result:?}");
         assert!(matches!(result, Err(Error::CrateNotFoundOnIndex)));
     }
@@ -238,7 +448,7 @@ mod tests {
         let temp_dir = tempfile::tempdir().unwrap();
         let location = temp_dir.path().to_str().unwrap();
         let setup = Setup {
-            crate_: String::from(TEST_CRATE_NAME),
+            crate_: Some(String::from(TEST_CRATE_NAME)),
             dependencies: SelectVersion::Latest,
             no_replace: false,
             location: location.to_string(),
@@ -246,12 +456,12 @@ mod tests {
         };
 
         // First run should succeed
-        let result1 = setup.run();
+        let result1 = setup.run(&IndexOptions::default(), OutputFormat::Text);
         log::debug!("Result1: {result1:?}");
         assert!(result1.is_ok());
 
         // Second run with no_replace should still succeed
-        let result2 = setup.run();
+        let result2 = setup.run(&IndexOptions::default(), OutputFormat::Text);
         log::debug!("Result2: {result2:?}");
         assert!(result2.is_ok());
     }
@@ -262,7 +472,7 @@ mod tests {
         let temp_dir = tempfile::tempdir().unwrap();
         let location = temp_dir.path().to_str().unwrap();
         let setup = Setup {
-            crate_: String::from(TEST_CRATE_NAME),
+            crate_: Some(String::from(TEST_CRATE_NAME)),
             dependencies: SelectVersion::Latest,
             no_replace: true,
             location: location.to_string(),
@@ -270,13 +480,299 @@ mod tests {
         };
 
         // First run should succeed
-        let result1 = setup.run();
+        let result1 = setup.run(&IndexOptions::default(), OutputFormat::Text);
         log::debug!("Result1: {result1:?}");
         assert!(result1.is_ok());
 
         // Second run with no_replace should not succeed
-        let result2 = setup.run();
+        let result2 = setup.run(&IndexOptions::default(), OutputFormat::Text);
         log::debug!("Result2: {result2:?}");
         assert!(result2.is_err());
     }
+
+    fn selected(setup: &Setup, index_crate: &IndexKrate) -> Result<Vec<String>, Error> {
+        setup.select_versions(index_crate).map(|versions| {
+            versions
+                .iter()
+                .map(|version| version.version.to_string())
+                .collect()
+        })
+    }
+
+    #[test]
+    fn test_select_versions() {
+        let index_crate = crate::diff::tests::two_versions();
+        let setup = |args: &[&str]| {
+            let args = ["setup"].iter().chain(args).chain(&["diffed"]);
+            Setup::try_parse_from(args).unwrap()
+        };
+
+        assert_eq!(selected(&setup(&[]), &index_crate).unwrap(), ["1.3.0"]);
+        assert_eq!(
+            selected(&setup(&["--dependencies", "earliest"]), &index_crate).unwrap(),
+            ["1.2.0"]
+        );
+        assert_eq!(
+            selected(&setup(&["--version", "1.2.0"]), &index_crate).unwrap(),
+            ["1.2.0"]
+        );
+        assert_eq!(
+            selected(&setup(&["--req", ">=1.2"]), &index_crate).unwrap(),
+            ["1.2.0", "1.3.0"]
+        );
+        assert!(matches!(
+            selected(&setup(&["--version", "9.9.9"]), &index_crate),
+            Err(Error::VersionNotFound(_, _))
+        ));
+        assert_eq!(
+            selected(&setup(&["--req", "^2"]), &index_crate)
+                .unwrap_err()
+                .to_string(),
+            "No version of diffed matches: requirement ^2"
+        );
+    }
+
+    #[test]
+    fn test_parse_version_selection() {
+        let setup = Setup::try_parse_from(["setup", "--all-versions", "forestry"]).unwrap();
+        assert!(setup.all_versions);
+
+        let result =
+            Setup::try_parse_from(["setup", "--version", "1.0.0", "--req", "^1", "forestry"]);
+        assert!(result.is_err());
+        let result = Setup::try_parse_from(["setup", "--req", "not a req", "forestry"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_closure_options() {
+        let setup = Setup::try_parse_from([
+            "setup",
+            "--optional",
+            "--no-build",
+            "--exclude-target",
+            "windows",
+            "--exclude-target",
+            "wasm",
+            "forestry",
+        ])
+        .unwrap();
+
+        let options = setup.walk_options();
+        assert!(options.optional);
+        assert!(!options.dev);
+        assert!(!options.build);
+        assert_eq!(options.exclude_targets, ["windows", "wasm"]);
+    }
+
+    #[test]
+    fn test_setup_run_from_local_registry() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let location = temp_dir.path().join("registry");
+        let options = IndexOptions::new(crate::Registry::Local(crate::tests::TEST_REGISTRY.into()));
+        let setup = Setup::try_parse_from([
+            "setup",
+            "--location",
+            location.to_str().unwrap(),
+            "forestry",
+        ])
+        .unwrap();
+
+        let result = setup.run(&options, OutputFormat::Text);
+
+        assert!(result.is_ok(), "Expected Ok, got {result:?}");
+        for file in ["forestry-1.4.1.crate", "colored-2.1.0.crate"] {
+            assert!(location.join(file).is_file(), "Expected {file} to be added");
+        }
+    }
+
+    #[test]
+    fn test_setup_run_from_lock() {
+        let url = crate::tests::serve_test_registry(None);
+        let (_cache_dir, index_location) = crate::tests::test_sparse_location(url.clone());
+        let combo_index = crate::get_remote_combo_index(index_location, None, None).unwrap();
+        let temp_dir = tempfile::tempdir().unwrap();
+        let location = temp_dir.path().join("registry");
+        let lockfile = temp_dir.path().join("Cargo.lock");
+        // colored is locked from crates.io rather than the registry queried
+        std::fs::write(
+            &lockfile,
+            format!(
+                r#"version = 4
+
+[[package]]
+name = "forestry"
+version = "1.4.1"
+source = "{url}"
+checksum = "d0fd162274396f9b49d7e3d56f303a12917e67ff7b6d8de27090dd2c720eb7b0"
+
+[[package]]
+name = "colored"
+version = "2.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "cbf2150cce219b664a8a70df7a1f933836724b503f8a413af9365b4dcc4d90b8"
+"#
+            ),
+        )
+        .unwrap();
+        let lockfile = lockfile.to_str().unwrap();
+        let setup = Setup::try_parse_from([
+            "setup",
+            "--from-lock",
+            lockfile,
+            "--location",
+            location.to_str().unwrap(),
+        ])
+        .unwrap();
+
+        let packages =
+            lockfile::registry_packages(Path::new(lockfile), &crate::Registry::Url(url)).unwrap();
+        let result = setup.run_from_lock(lockfile, &packages, &combo_index, OutputFormat::Text);
+
+        assert!(result.is_ok(), "Expected Ok, got {result:?}");
+        assert!(location.join("forestry-1.4.1.crate").is_file());
+        assert!(!location.join("colored-2.1.0.crate").exists());
+        assert!(!location.join("index/co/lo/colored").exists());
+        let forestry =
+            IndexKrate::new(location.join("index/fo/re/forestry").to_str().unwrap()).unwrap();
+        let versions = forestry
+            .versions
+            .iter()
+            .map(|version| version.version.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(versions, ["1.4.1"]);
+    }
+
+    #[test]
+    fn test_setup_run_from_lock_checksum_mismatch() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let location = temp_dir.path().join("registry");
+        let options = IndexOptions::new(crate::Registry::Local(crate::tests::TEST_REGISTRY.into()));
+        let setup = Setup::try_parse_from([
+            "setup",
+            "--from-lock",
+            "tests/lockfile/mismatched.lock",
+            "--location",
+            location.to_str().unwrap(),
+        ])
+        .unwrap();
+
+        let result = setup.run(&options, OutputFormat::Text);
+
+        assert!(matches!(result, Err(Error::ChecksumMismatch(name, _)) if name == "forestry"));
+        assert!(!location.exists());
+    }
+
+    #[test]
+    fn test_setup_run_from_manifest_no_matching_version() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let location = temp_dir.path().join("registry");
+        let options = IndexOptions::new(crate::Registry::Local(crate::tests::TEST_REGISTRY.into()));
+        let setup = Setup::try_parse_from([
+            "setup",
+            "--manifest",
+            "tests/manifest/unmatched.toml",
+            "--location",
+            location.to_str().unwrap(),
+        ])
+        .unwrap();
+
+        let result = setup.run(&options, OutputFormat::Text);
+
+        assert!(matches!(result, Err(Error::NoMatchingVersion(name, _)) if name == "forestry"));
+        assert!(!location.exists());
+    }
+
+    #[test]
+    fn test_parse_manifest() {
+        let setup =
+            Setup::try_parse_from(["setup", "--manifest", "Cargo.toml", "--no-build"]).unwrap();
+        assert_eq!(setup.manifest.as_deref(), Some("Cargo.toml"));
+        assert!(!setup.walk_options().build);
+
+        let result = Setup::try_parse_from(["setup", "--manifest", "Cargo.toml", "forestry"]);
+        assert!(result.is_err());
+        let result = Setup::try_parse_from([
+            "setup",
+            "--manifest",
+            "Cargo.toml",
+            "--from-lock",
+            "Cargo.lock",
+        ]);
+        assert!(result.is_err());
+        let result = Setup::try_parse_from([
+            "setup",
+            "--manifest",
+            "Cargo.toml",
+            "--dependencies",
+            "earliest",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_append() {
+        let setup = Setup::try_parse_from(["setup", "--append", "forestry"]).unwrap();
+        assert!(setup.append);
+
+        let result = Setup::try_parse_from(["setup", "--append", "--no-replace", "forestry"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_registry_add() {
+        let registry = RegistryCommand::try_parse_from([
+            "registry",
+            "add",
+            "--location",
+            "registry",
+            "forestry",
+        ])
+        .unwrap();
+
+        let RegistrySubcommand::Add(setup) = registry.command;
+        assert_eq!(setup.crate_.as_deref(), Some("forestry"));
+        assert_eq!(setup.location, "registry");
+    }
+
+    #[test]
+    fn test_parse_from_lock() {
+        let setup = Setup::try_parse_from(["setup", "--from-lock", "Cargo.lock"]).unwrap();
+        assert_eq!(setup.from_lock.as_deref(), Some("Cargo.lock"));
+        assert!(setup.crate_.is_none());
+
+        let result = Setup::try_parse_from(["setup", "--from-lock", "Cargo.lock", "forestry"]);
+        assert!(result.is_err());
+        let result = Setup::try_parse_from([
+            "setup",
+            "--from-lock",
+            "Cargo.lock",
+            "--dependencies",
+            "none",
+        ]);
+        assert!(result.is_err());
+        let result = Setup::try_parse_from(["setup"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_setup_run_version_not_found() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let location = temp_dir.path().join("registry");
+        let options = IndexOptions::new(crate::Registry::Local(crate::tests::TEST_REGISTRY.into()));
+        let setup = Setup::try_parse_from([
+            "setup",
+            "--version",
+            "1.4.0",
+            "--location",
+            location.to_str().unwrap(),
+            "forestry",
+        ])
+        .unwrap();
+
+        let result = setup.run(&options, OutputFormat::Text);
+
+        assert!(matches!(result, Err(Error::VersionNotFound(_, _))));
+        assert!(!location.exists());
+    }
 }
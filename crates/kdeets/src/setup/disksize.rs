@@ -1,6 +1,8 @@
 use std::{fmt::Display, ops::AddAssign};
 
-#[derive(Debug, PartialEq, Eq)]
+use serde::Serialize;
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
 pub(crate) struct DiskSize(u64);
 
 impl DiskSize {
@@ -12,6 +14,10 @@ impl DiskSize {
     pub fn new(val: u64) -> Self {
         Self(val)
     }
+
+    pub fn bytes(&self) -> u64 {
+        self.0
+    }
 }
 
 impl Display for DiskSize {
@@ -0,0 +1,208 @@
+//! The registry packages locked in a `Cargo.lock`
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde::Deserialize;
+use tame_index::{IndexKrate, IndexVersion};
+
+use crate::{Error, Registry};
+
+#[derive(Debug, Deserialize)]
+struct Lockfile {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+/// A package and the exact version of it locked in the lockfile
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub(crate) struct LockedPackage {
+    name: String,
+    version: String,
+    /// Where the package comes from, absent for workspace members and path
+    /// dependencies
+    source: Option<String>,
+    /// The SHA-256 checksum of the `.crate` file, recorded for registry
+    /// packages
+    checksum: Option<String>,
+}
+
+impl LockedPackage {
+    fn is_from_registry(&self) -> bool {
+        self.source
+            .as_deref()
+            .is_some_and(|source| source.starts_with("registry+") || source.starts_with("sparse+"))
+    }
+
+    /// The version of the crate locked, once its checksum on the index is
+    /// confirmed to match the lockfile
+    pub(crate) fn find_in<'k>(
+        &self,
+        index_crate: &'k IndexKrate,
+    ) -> Result<&'k IndexVersion, Error> {
+        let index_version = index_crate
+            .versions
+            .iter()
+            .find(|index_version| index_version.version == self.version.as_str())
+            .ok_or_else(|| Error::VersionNotFound(self.name.clone(), self.version.clone()))?;
+
+        let Some(checksum) = &self.checksum else {
+            return Err(Error::MissingChecksum(
+                self.name.clone(),
+                self.version.clone(),
+            ));
+        };
+        if !index_version
+            .checksum
+            .to_string()
+            .eq_ignore_ascii_case(checksum)
+        {
+            return Err(Error::ChecksumMismatch(
+                self.name.clone(),
+                self.version.clone(),
+            ));
+        }
+
+        Ok(index_version)
+    }
+}
+
+/// The packages locked in the lockfile from the registry queried, grouped by
+/// crate name. Packages from other registries, git and path dependencies and
+/// workspace members are left out.
+pub(crate) fn registry_packages(
+    path: &Path,
+    registry: &Registry,
+) -> Result<BTreeMap<String, Vec<LockedPackage>>, Error> {
+    let contents = std::fs::read_to_string(path)?;
+    let lockfile: Lockfile = toml::from_str(&contents)
+        .map_err(|e| Error::InvalidLockfile(path.display().to_string(), e.to_string()))?;
+
+    let mut packages: BTreeMap<String, Vec<LockedPackage>> = BTreeMap::new();
+    for package in lockfile.package {
+        let Some(source) = package
+            .source
+            .as_deref()
+            .filter(|_| package.is_from_registry())
+        else {
+            log::debug!(
+                "Skipping {} {}, which is not from a registry",
+                package.name,
+                package.version
+            );
+            continue;
+        };
+        if !registry.serves_source(source)? {
+            log::info!(
+                "Skipping {} {}, which is from {source} rather than the registry queried",
+                package.name,
+                package.version
+            );
+            continue;
+        }
+        packages
+            .entry(package.name.clone())
+            .or_default()
+            .push(package);
+    }
+    Ok(packages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORESTRY_CHECKSUM: &str =
+        "d0fd162274396f9b49d7e3d56f303a12917e67ff7b6d8de27090dd2c720eb7b0";
+
+    fn locked(version: &str, checksum: Option<&str>) -> LockedPackage {
+        LockedPackage {
+            name: "forestry".to_string(),
+            version: version.to_string(),
+            source: Some("registry+https://github.com/rust-lang/crates.io-index".to_string()),
+            checksum: checksum.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_registry_packages() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("Cargo.lock");
+        std::fs::write(
+            &path,
+            format!(
+                r#"version = 4
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = ["forestry", "local"]
+
+[[package]]
+name = "forestry"
+version = "1.4.1"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "{FORESTRY_CHECKSUM}"
+
+[[package]]
+name = "forestry"
+version = "1.3.0"
+source = "sparse+https://index.crates.io/"
+checksum = "{FORESTRY_CHECKSUM}"
+
+[[package]]
+name = "private"
+version = "0.3.0"
+source = "sparse+https://example.com/index"
+checksum = "{FORESTRY_CHECKSUM}"
+
+[[package]]
+name = "forked"
+version = "0.2.0"
+source = "git+https://github.com/example/forked#0123456789abcdef"
+"#
+            ),
+        )
+        .unwrap();
+
+        let packages = registry_packages(&path, &Registry::CratesIo).unwrap();
+
+        assert_eq!(packages.keys().collect::<Vec<_>>(), ["forestry"]);
+        let versions = packages["forestry"]
+            .iter()
+            .map(|package| package.version.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(versions, ["1.4.1", "1.3.0"]);
+
+        let registry = Registry::Url("sparse+https://example.com/index/".to_string());
+        let packages = registry_packages(&path, &registry).unwrap();
+        assert_eq!(packages.keys().collect::<Vec<_>>(), ["private"]);
+    }
+
+    #[test]
+    fn test_registry_packages_invalid() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("Cargo.lock");
+        std::fs::write(&path, "[[package]]\nname = 1\n").unwrap();
+
+        let result = registry_packages(&path, &Registry::CratesIo);
+
+        assert!(matches!(result, Err(Error::InvalidLockfile(..))));
+    }
+
+    #[test]
+    fn test_find_in() {
+        let index_crate = IndexKrate::new("tests/registry/index/fo/re/forestry").unwrap();
+
+        let found = locked("1.4.1", Some(FORESTRY_CHECKSUM))
+            .find_in(&index_crate)
+            .unwrap();
+        assert_eq!(found.version, "1.4.1");
+
+        let result = locked("1.4.1", Some(&"0".repeat(64))).find_in(&index_crate);
+        assert!(matches!(result, Err(Error::ChecksumMismatch(..))));
+        let result = locked("1.4.1", None).find_in(&index_crate);
+        assert!(matches!(result, Err(Error::MissingChecksum(..))));
+        let result = locked("9.9.9", Some(FORESTRY_CHECKSUM)).find_in(&index_crate);
+        assert!(matches!(result, Err(Error::VersionNotFound(..))));
+    }
+}
@@ -0,0 +1,555 @@
+//! The registry dependencies declared in a `Cargo.toml` and, for a workspace,
+//! in the manifests of its members
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+};
+
+use semver::VersionReq;
+use serde::Deserialize;
+
+use crate::Error;
+use crate::walk::{Requirement, WalkOptions};
+
+type DependencyTable = BTreeMap<String, DependencySpec>;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Manifest {
+    #[serde(default)]
+    dependencies: DependencyTable,
+    #[serde(default)]
+    dev_dependencies: DependencyTable,
+    #[serde(default)]
+    build_dependencies: DependencyTable,
+    #[serde(default)]
+    target: BTreeMap<String, TargetDependencies>,
+    #[serde(default)]
+    features: BTreeMap<String, Vec<String>>,
+    workspace: Option<Workspace>,
+}
+
+/// The dependencies of a `[target.'cfg(...)']` table
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct TargetDependencies {
+    #[serde(default)]
+    dependencies: DependencyTable,
+    #[serde(default)]
+    dev_dependencies: DependencyTable,
+    #[serde(default)]
+    build_dependencies: DependencyTable,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Workspace {
+    #[serde(default)]
+    members: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    dependencies: DependencyTable,
+}
+
+/// A dependency, given either as a version requirement or as a table
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum DependencySpec {
+    Version(String),
+    Detailed(DetailedDependency),
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct DetailedDependency {
+    version: Option<String>,
+    /// The name of the crate, when the dependency is renamed
+    package: Option<String>,
+    path: Option<String>,
+    git: Option<String>,
+    #[serde(default)]
+    features: Vec<String>,
+    #[serde(alias = "default_features")]
+    default_features: Option<bool>,
+    #[serde(default)]
+    optional: bool,
+    /// The registry the crate is from, when it is not crates.io
+    registry: Option<String>,
+    /// The dependency is inherited from `[workspace.dependencies]`
+    #[serde(default)]
+    workspace: bool,
+}
+
+impl DependencySpec {
+    fn detailed(&self) -> DetailedDependency {
+        match self {
+            Self::Version(version) => DetailedDependency {
+                version: Some(version.clone()),
+                ..DetailedDependency::default()
+            },
+            Self::Detailed(detailed) => detailed.clone(),
+        }
+    }
+}
+
+/// The requirements on registry crates declared by the manifest, including
+/// the dev and build dependencies, and by the members of its workspace. Path
+/// and git dependencies are left out, as are dependencies from a registry
+/// other than the one named `registry`, optional dependencies not enabled by
+/// default, build dependencies and target specific dependencies when the
+/// options exclude them.
+///
+/// A manifest that is a member of a workspace inherits dependencies from the
+/// `[workspace.dependencies]` of the workspace root in a parent directory.
+pub(crate) fn requirements(
+    path: &Path,
+    options: &WalkOptions,
+    registry: Option<&str>,
+) -> Result<Vec<Requirement>, Error> {
+    let manifest = read(path)?;
+    let workspace = manifest.workspace.as_ref();
+    let workspace_dependencies = match workspace {
+        Some(workspace) => workspace.dependencies.clone(),
+        None => workspace_root_dependencies(path)?,
+    };
+    let context = Context {
+        workspace_dependencies: &workspace_dependencies,
+        options,
+        registry,
+    };
+
+    let mut requirements = vec![];
+    add_requirements(&mut requirements, path, &manifest, &context)?;
+
+    if let Some(workspace) = workspace {
+        // A bare `Cargo.toml` has an empty parent, and members such as `.`
+        // name the root by another path, so both are compared canonicalised
+        let path = path.canonicalize()?;
+        let root = path.parent().unwrap_or(Path::new("."));
+        for member in members(root, workspace)? {
+            let member_path = member.join("Cargo.toml").canonicalize()?;
+            if member_path == path {
+                continue;
+            }
+            log::debug!("Reading workspace member {}", member_path.display());
+            let member_manifest = read(&member_path)?;
+            add_requirements(&mut requirements, &member_path, &member_manifest, &context)?;
+        }
+    }
+    Ok(requirements)
+}
+
+/// What the requirements of each manifest in a workspace are read with
+struct Context<'c> {
+    workspace_dependencies: &'c DependencyTable,
+    options: &'c WalkOptions,
+    registry: Option<&'c str>,
+}
+
+/// The `[workspace.dependencies]` of the workspace root found in a parent
+/// directory of the manifest, or none when the manifest is not in a workspace
+fn workspace_root_dependencies(path: &Path) -> Result<DependencyTable, Error> {
+    let path = path.canonicalize()?;
+    for dir in path.ancestors().skip(2) {
+        let root_path = dir.join("Cargo.toml");
+        if !root_path.is_file() {
+            continue;
+        }
+        if let Some(workspace) = read(&root_path)?.workspace {
+            log::debug!("Found workspace root {}", root_path.display());
+            return Ok(workspace.dependencies);
+        }
+    }
+    Ok(DependencyTable::new())
+}
+
+fn read(path: &Path) -> Result<Manifest, Error> {
+    let contents = std::fs::read_to_string(path)?;
+    toml::from_str(&contents)
+        .map_err(|e| Error::InvalidManifest(path.display().to_string(), e.to_string()))
+}
+
+fn add_requirements(
+    requirements: &mut Vec<Requirement>,
+    path: &Path,
+    manifest: &Manifest,
+    context: &Context,
+) -> Result<(), Error> {
+    let options = context.options;
+    let enabled = default_dependencies(manifest);
+    let mut tables = vec![&manifest.dependencies, &manifest.dev_dependencies];
+    if options.build {
+        tables.push(&manifest.build_dependencies);
+    }
+    for (target, dependencies) in &manifest.target {
+        if options.excludes_target(target) {
+            log::debug!("Skipping dependencies for target {target}");
+            continue;
+        }
+        tables.push(&dependencies.dependencies);
+        tables.push(&dependencies.dev_dependencies);
+        if options.build {
+            tables.push(&dependencies.build_dependencies);
+        }
+    }
+
+    for (key, spec) in tables.into_iter().flatten() {
+        let mut dependency = spec.detailed();
+        if dependency.workspace {
+            let Some(inherited) = context.workspace_dependencies.get(key) else {
+                return Err(Error::InvalidManifest(
+                    path.display().to_string(),
+                    format!("{key} is not in [workspace.dependencies]"),
+                ));
+            };
+            dependency = inherit(inherited.detailed(), dependency);
+        }
+
+        if dependency.path.is_some() || dependency.git.is_some() {
+            log::debug!("Skipping {key}, which is not from a registry");
+            continue;
+        }
+        let dependency_registry = dependency.registry.as_deref().unwrap_or("crates-io");
+        if context
+            .registry
+            .is_some_and(|registry| registry != dependency_registry)
+        {
+            log::debug!("Skipping {key}, which is from the registry {dependency_registry}");
+            continue;
+        }
+        if dependency.optional && !options.optional && !enabled.contains(key) {
+            log::debug!("Skipping {key}, an optional dependency not enabled by default");
+            continue;
+        }
+        let req = match &dependency.version {
+            Some(version) => VersionReq::parse(version)?,
+            None => VersionReq::STAR,
+        };
+        requirements.push(Requirement {
+            name: dependency.package.unwrap_or_else(|| key.clone()),
+            req,
+            features: dependency.features.into_iter().collect(),
+            default_features: dependency.default_features.unwrap_or(true),
+        });
+    }
+    Ok(())
+}
+
+/// The dependency inherited from the workspace, with the features the member
+/// adds to it and whether the member makes it optional
+fn inherit(mut inherited: DetailedDependency, member: DetailedDependency) -> DetailedDependency {
+    inherited.features.extend(member.features);
+    inherited.optional = member.optional;
+    inherited
+}
+
+/// The optional dependencies enabled by the default features of the
+/// manifest, by the name used in feature names
+fn default_dependencies(manifest: &Manifest) -> BTreeSet<String> {
+    let mut pending = vec!["default"];
+    let mut walked = BTreeSet::new();
+    let mut enabled = BTreeSet::new();
+    while let Some(name) = pending.pop() {
+        if !walked.insert(name) {
+            continue;
+        }
+        for enable in manifest.features.get(name).into_iter().flatten() {
+            if let Some(dependency) = enable.strip_prefix("dep:") {
+                enabled.insert(dependency.to_string());
+            } else if let Some((dependency, _)) = enable.split_once('/') {
+                // Weak `dependency?/feature` entries do not enable the dependency
+                if !dependency.ends_with('?') {
+                    enabled.insert(dependency.to_string());
+                }
+            } else if manifest.features.contains_key(enable) {
+                pending.push(enable);
+            } else {
+                // The implicit feature of an optional dependency
+                enabled.insert(enable.clone());
+            }
+        }
+    }
+    enabled
+}
+
+/// The directories of the workspace members, expanding `*` in the paths
+fn members(root: &Path, workspace: &Workspace) -> Result<Vec<PathBuf>, Error> {
+    let excluded = workspace
+        .exclude
+        .iter()
+        .map(|exclude| root.join(exclude))
+        .collect::<Vec<_>>();
+
+    let mut members = vec![];
+    for pattern in &workspace.members {
+        let mut paths = vec![root.to_path_buf()];
+        for component in Path::new(pattern).components() {
+            let component = component.as_os_str().to_string_lossy();
+            if !component.contains('*') {
+                paths = paths.iter().map(|path| path.join(&*component)).collect();
+                continue;
+            }
+            let mut expanded = vec![];
+            for path in &paths {
+                for entry in std::fs::read_dir(path)? {
+                    let entry = entry?;
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if entry.file_type()?.is_dir() && matches_wildcard(&component, &name) {
+                        expanded.push(entry.path());
+                    }
+                }
+            }
+            expanded.sort();
+            paths = expanded;
+        }
+        members.extend(
+            paths
+                .into_iter()
+                .filter(|path| path.join("Cargo.toml").is_file() && !excluded.contains(path)),
+        );
+    }
+    Ok(members)
+}
+
+/// Whether the name matches the pattern, where `*` matches any text
+fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts = parts.collect::<Vec<_>>();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    /// A workspace whose root package depends on `forestry`, with members
+    /// under `crates/` inheriting a workspace dependency and a path
+    /// dependency on each other. The member has optional dependencies, one
+    /// enabled by default, and a dependency from another registry.
+    fn workspace() -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        write(
+            root,
+            "Cargo.toml",
+            r#"[package]
+name = "app"
+version = "0.1.0"
+
+[workspace]
+members = ["crates/*"]
+exclude = ["crates/excluded"]
+
+[workspace.dependencies]
+colored = { version = "2", default-features = false }
+
+[dependencies]
+forestry = "1.4"
+member = { path = "crates/member" }
+
+[build-dependencies]
+cc = "1"
+
+[target.'cfg(windows)'.dependencies]
+windows-sys = "0.48"
+
+[target.'cfg(not(windows))'.dependencies]
+libc = "0.2"
+"#,
+        );
+        write(
+            root,
+            "crates/member/Cargo.toml",
+            r#"[package]
+name = "member"
+version = "0.1.0"
+
+[features]
+default = ["logging"]
+logging = ["dep:log-alias"]
+
+[dependencies]
+colored = { workspace = true, features = ["no-color"] }
+log-alias = { version = "0.4", package = "log", optional = true }
+private = { version = "1", registry = "private" }
+serde = { version = "1", optional = true }
+
+[dev-dependencies]
+some_crate = "0.2"
+"#,
+        );
+        write(
+            root,
+            "crates/excluded/Cargo.toml",
+            "[package]\nname = \"excluded\"\n\n[dependencies]\nserde = \"1\"\n",
+        );
+        std::fs::create_dir_all(root.join("crates/not_a_crate")).unwrap();
+        temp_dir
+    }
+
+    fn names(requirements: &[Requirement]) -> Vec<&str> {
+        requirements
+            .iter()
+            .map(|requirement| requirement.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_requirements_of_workspace() {
+        let temp_dir = workspace();
+
+        let requirements = requirements(
+            &temp_dir.path().join("Cargo.toml"),
+            &WalkOptions::default(),
+            Some("crates-io"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            names(&requirements),
+            [
+                "forestry",
+                "cc",
+                "libc",
+                "windows-sys",
+                "colored",
+                "log",
+                "some_crate"
+            ]
+        );
+        let colored = &requirements[4];
+        assert_eq!(colored.req, VersionReq::parse("2").unwrap());
+        assert!(!colored.default_features);
+        assert!(colored.features.contains("no-color"));
+    }
+
+    #[test]
+    fn test_requirements_options() {
+        let temp_dir = workspace();
+        let options = WalkOptions {
+            build: false,
+            exclude_targets: vec!["windows".to_string()],
+            ..WalkOptions::default()
+        };
+
+        let requirements = requirements(
+            &temp_dir.path().join("Cargo.toml"),
+            &options,
+            Some("crates-io"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            names(&requirements),
+            ["forestry", "libc", "colored", "log", "some_crate"]
+        );
+    }
+
+    #[test]
+    fn test_requirements_of_member() {
+        let temp_dir = workspace();
+
+        let requirements = requirements(
+            &temp_dir.path().join("crates/member/Cargo.toml"),
+            &WalkOptions::default(),
+            Some("crates-io"),
+        )
+        .unwrap();
+
+        assert_eq!(names(&requirements), ["colored", "log", "some_crate"]);
+        let colored = &requirements[0];
+        assert_eq!(colored.req, VersionReq::parse("2").unwrap());
+        assert!(!colored.default_features);
+    }
+
+    #[test]
+    fn test_requirements_optional_and_registry() {
+        let temp_dir = workspace();
+        let path = temp_dir.path().join("crates/member/Cargo.toml");
+        let options = WalkOptions {
+            optional: true,
+            ..WalkOptions::default()
+        };
+
+        let private = requirements(&path, &options, Some("private")).unwrap();
+        assert_eq!(names(&private), ["private"]);
+
+        let every_registry = requirements(&path, &options, None).unwrap();
+        assert_eq!(
+            names(&every_registry),
+            ["colored", "log", "private", "serde", "some_crate"]
+        );
+    }
+
+    #[test]
+    fn test_requirements_of_root_member() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write(
+            temp_dir.path(),
+            "Cargo.toml",
+            "[package]\nname = \"app\"\n\n[workspace]\nmembers = [\".\", \"member\"]\n\n[dependencies]\nforestry = \"1.4\"\n",
+        );
+        write(
+            temp_dir.path(),
+            "member/Cargo.toml",
+            "[package]\nname = \"member\"\n\n[dependencies]\ncolored = \"2\"\n",
+        );
+
+        let requirements = requirements(
+            &temp_dir.path().join("Cargo.toml"),
+            &WalkOptions::default(),
+            Some("crates-io"),
+        )
+        .unwrap();
+
+        assert_eq!(names(&requirements), ["forestry", "colored"]);
+    }
+
+    #[test]
+    fn test_requirements_missing_workspace_dependency() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write(
+            temp_dir.path(),
+            "Cargo.toml",
+            "[package]\nname = \"app\"\n\n[dependencies]\ncolored.workspace = true\n",
+        );
+
+        let result = requirements(
+            &temp_dir.path().join("Cargo.toml"),
+            &WalkOptions::default(),
+            Some("crates-io"),
+        );
+
+        assert!(matches!(result, Err(Error::InvalidManifest(..))));
+    }
+
+    #[test]
+    fn test_matches_wildcard() {
+        assert!(matches_wildcard("*", "member"));
+        assert!(matches_wildcard("kdeets-*", "kdeets-cli"));
+        assert!(matches_wildcard("*-cli", "kdeets-cli"));
+        assert!(matches_wildcard("a*b*c", "aXbYc"));
+        assert!(!matches_wildcard("a*b", "ab-a"));
+        assert!(!matches_wildcard("kdeets-*", "member"));
+    }
+}
@@ -1,39 +1,59 @@
-use std::{fmt::Display, fs};
+use std::{fmt::Display, fs, io::Seek};
 
 use crate::ComboIndex;
 use colorful::Colorful;
+use serde::Serialize;
 use tame_index::{
-    IndexDependency, IndexKrate, KrateName, PathBuf,
-    index::{
-        FileLock,
-        local::{LocalRegistryBuilder, ValidKrate, builder::Client},
-    },
+    IndexKrate, IndexVersion, KrateName, PathBuf,
+    index::{FileLock, LocalRegistry, local::LocalRegistryBuilder},
 };
 
-use crate::{Error, LINE_CHAR, SETUP_HEADER};
+use crate::walk::{DependencyClosure, WalkOptions};
+use crate::{Error, LINE_CHAR, Report, SETUP_HEADER};
 
 use super::DiskSize;
 
 pub(crate) struct SetupTestOutputBuilder {
     #[allow(dead_code)]
-    index_crate: IndexKrate,
+    index_crate: Option<IndexKrate>,
     header: String,
     registry_path: PathBuf,
     registry: Option<LocalRegistryBuilder>,
+    /// The existing registry crates are added to, when appending
+    existing: Option<LocalRegistry>,
     crates: Vec<String>,
+    /// The bytes written for each crate added, in the order of `crates`
+    crate_bytes: Vec<DiskSize>,
+    versions: Vec<String>,
     total: DiskSize,
 }
 
+/// The directory, inside the registry, new `.crate` files are downloaded to
+/// before they are moved into an existing registry
+const STAGING_DIR: &str = ".kdeets-staging";
+
 impl SetupTestOutputBuilder {
     pub(crate) fn new(index_crate: IndexKrate, registry: &str) -> Self {
+        let mut builder = Self::titled(index_crate.name(), registry);
+        builder.index_crate = Some(index_crate);
+        builder
+    }
+
+    /// A builder for a registry holding the crates needed by a lockfile or
+    /// manifest
+    pub(crate) fn for_file(file: &str, registry: &str) -> Self {
+        Self::titled(file, registry)
+    }
+
+    fn titled(title: &str, registry: &str) -> Self {
         let mut header = String::from("\n  ");
         header.push_str(SETUP_HEADER);
         header.push(' ');
-        header.push_str(index_crate.name().cyan().to_string().as_str());
+        header.push_str(title.cyan().to_string().as_str());
         header.push('.');
         header.push_str("\n  ");
         let mut i = 0;
-        while i < SETUP_HEADER.len() + 2 + index_crate.name().len() {
+        while i < SETUP_HEADER.len() + 2 + title.len() {
             header.push(LINE_CHAR);
             i += 1;
         }
@@ -42,11 +62,14 @@ impl SetupTestOutputBuilder {
         let registry_path = PathBuf::from(registry);
 
         Self {
-            index_crate,
+            index_crate: None,
             header,
             registry_path,
             registry: None,
+            existing: None,
             crates: Vec::new(),
+            crate_bytes: Vec::new(),
+            versions: Vec::new(),
             total: DiskSize::zero(),
         }
     }
@@ -74,20 +97,40 @@ impl SetupTestOutputBuilder {
         Ok(self)
     }
 
-    pub(crate) fn insert_crate(&mut self, index_crate: &IndexKrate) -> Result<(), Error> {
+    /// Opens the registry to add crates to it, keeping the crates and
+    /// versions it already holds. A registry that does not exist yet is
+    /// created.
+    pub(crate) fn open_local_registry(&mut self) -> Result<&mut Self, Error> {
+        if !self.registry_path.join("index").is_dir() {
+            return self.initialise_local_registry(true);
+        }
+
+        let registry = LocalRegistry::open(self.registry_path.clone(), false)?;
+        log::debug!("Opened registry at {}", self.registry_path);
+        self.existing = Some(registry);
+        Ok(self)
+    }
+
+    pub(crate) fn insert_crate(
+        &mut self,
+        index_crate: &IndexKrate,
+        combo_index: &ComboIndex,
+    ) -> Result<(), Error> {
+        if self.existing.is_some() {
+            return self.merge_crate(index_crate, combo_index);
+        }
+
         let Some(registry_builder) = &mut self.registry else {
             return Err(Error::LocalRegistryBuilderNotSet);
         };
 
-        let client = Client::build(crate::get_client_builder())?;
-        let index = crate::get_sparse_index()?;
-        let index_config = index.index_config()?;
+        let index_config = combo_index.index_config()?;
 
         let mut krates = vec![];
 
         for version in &index_crate.versions {
             log::debug!("Downloaded for version {}", version.version);
-            let krate = ValidKrate::download(&client, &index_config, version)?;
+            let krate = combo_index.download(&index_config, version)?;
             krates.push(krate);
         }
 
@@ -95,56 +138,242 @@ impl SetupTestOutputBuilder {
         self.total += written;
         log::debug!("Inserted crate {} into registry", index_crate.name());
         self.crates.push(index_crate.name().to_string());
+        self.crate_bytes.push(DiskSize::new(written));
+        self.versions.extend(
+            index_crate
+                .versions
+                .iter()
+                .map(|version| format!("{} {}", version.name, version.version)),
+        );
+        Ok(())
+    }
+
+    /// Merges the versions of the crate into the existing registry. Only the
+    /// versions it does not hold, or whose `.crate` file is missing, are
+    /// downloaded, and the index entry keeps the versions already in it.
+    fn merge_crate(
+        &mut self,
+        index_crate: &IndexKrate,
+        combo_index: &ComboIndex,
+    ) -> Result<(), Error> {
+        let Some(existing) = &self.existing else {
+            return Err(Error::LocalRegistryBuilderNotSet);
+        };
+        let name: KrateName<'_> = index_crate.name().try_into()?;
+        let lock = FileLock::unlocked();
+        let held = existing.cached_krate(name, &lock)?;
+        let index_path = existing.krate_path(name);
+
+        let is_held = |version: &IndexVersion| {
+            held.as_ref().is_some_and(|held| {
+                held.versions
+                    .iter()
+                    .any(|held_version| held_version.version == version.version)
+            })
+        };
+        let missing = index_crate
+            .versions
+            .iter()
+            .filter(|version| {
+                !is_held(version) || !self.registry_path.join(crate_file(version)).is_file()
+            })
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            log::debug!("Registry already holds crate {}", index_crate.name());
+            return Ok(());
+        }
+
+        let staging_path = self.registry_path.join(STAGING_DIR);
+        if staging_path.exists() {
+            fs::remove_dir_all(&staging_path)?;
+        }
+        let staging = LocalRegistryBuilder::create(staging_path.clone())?;
+        let index_config = combo_index.index_config()?;
+        let mut krates = vec![];
+        for version in &missing {
+            log::debug!("Downloaded for version {}", version.version);
+            krates.push(combo_index.download(&index_config, version)?);
+        }
+        staging.insert(&with_versions(index_crate, &missing), &krates)?;
+
+        let mut written = 0;
+        for version in &missing {
+            let file = crate_file(version);
+            written += fs::metadata(staging_path.join(&file))?.len();
+            fs::rename(staging_path.join(&file), self.registry_path.join(&file))?;
+        }
+        fs::remove_dir_all(&staging_path)?;
+
+        let mut merged = held.unwrap_or_else(|| IndexKrate { versions: vec![] });
+        for version in &missing {
+            if !merged
+                .versions
+                .iter()
+                .any(|held_version| held_version.version == version.version)
+            {
+                merged.versions.push((*version).clone());
+            }
+        }
+        if let Some(parent) = index_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut index_entry = fs::File::create(&index_path)?;
+        merged.write_json_lines(&mut index_entry)?;
+        written += index_entry.stream_position()?;
+
+        self.total += written;
+        log::debug!("Merged crate {} into registry", index_crate.name());
+        self.crates.push(index_crate.name().to_string());
+        self.crate_bytes.push(DiskSize::new(written));
+        self.versions.extend(
+            missing
+                .iter()
+                .map(|version| format!("{} {}", version.name, version.version)),
+        );
         Ok(())
     }
 
+    /// Adds every crate in the dependency graph of the versions of the
+    /// crate, other than crates already added. Unless the options walk all
+    /// versions, only the versions of each crate selected for a requirement on
+    /// it are added.
     pub(crate) fn add_dependency_crates(
         &mut self,
-        dependencies: &[IndexDependency],
+        index_crate: &IndexKrate,
+        versions: &[&IndexVersion],
         combo_index: &ComboIndex,
+        options: WalkOptions,
     ) -> Result<(), Error> {
-        log::debug!("Adding {} dependencies", dependencies.len());
-        for dependency in dependencies {
-            let dependency_name = KrateName::crates_io(dependency.crate_name())?;
-            let lock = FileLock::unlocked();
-            let dependency_crate = combo_index.krate(dependency_name, true, &lock)?;
-            if let Some(dependency_crate) = dependency_crate {
-                self.insert_crate(&dependency_crate)?
+        let mut closure = DependencyClosure::new(combo_index, options);
+        closure.walk(index_crate, versions)?;
+
+        self.insert_closure(&closure, combo_index)
+    }
+
+    /// Adds every crate found by the dependency closure, other than crates
+    /// already added
+    pub(crate) fn insert_closure(
+        &mut self,
+        closure: &DependencyClosure,
+        combo_index: &ComboIndex,
+    ) -> Result<(), Error> {
+        for (dependency_crate, versions) in closure.dependencies() {
+            if self
+                .crates
+                .iter()
+                .any(|name| name == dependency_crate.name())
+            {
+                log::debug!("Crate {} already added", dependency_crate.name());
+                continue;
+            }
+
+            if closure.all_versions() {
+                self.insert_crate(dependency_crate, combo_index)?;
             } else {
-                log::warn!("Could not find dependency: {dependency_name}, skipping.");
+                self.insert_crate(&with_versions(dependency_crate, &versions), combo_index)?;
             }
         }
         Ok(())
     }
 
     pub(crate) fn finalize(self) -> Result<SetupTestOutput, Error> {
-        let Some(registry_builder) = self.registry else {
-            return Err(Error::LocalRegistryBuilderNotSet);
-        };
+        let appended = self.existing.is_some();
+        if appended {
+            LocalRegistry::open(self.registry_path.clone(), true)?;
+        } else {
+            let Some(registry_builder) = self.registry else {
+                return Err(Error::LocalRegistryBuilderNotSet);
+            };
 
-        let temp = registry_builder;
+            let temp = registry_builder;
 
-        let _local_registry = temp.finalize(true)?;
+            let _local_registry = temp.finalize(true)?;
+        }
 
         Ok(SetupTestOutput {
             header: self.header,
+            registry: self.registry_path.to_string(),
             crates: self.crates,
+            crate_bytes: self.crate_bytes,
+            versions: self.versions,
+            appended,
             total: self.total,
         })
     }
 }
 
-#[derive(Debug)]
+/// The name of the `.crate` file of the version in a local registry
+fn crate_file(version: &IndexVersion) -> String {
+    format!("{}-{}.crate", version.name, version.version)
+}
+
+/// A copy of the crate with only the versions given
+pub(crate) fn with_versions(index_crate: &IndexKrate, versions: &[&IndexVersion]) -> IndexKrate {
+    let mut selected = index_crate.clone();
+    selected
+        .versions
+        .retain(|version| versions.iter().any(|kept| kept.version == version.version));
+    selected
+}
+
+#[derive(Debug, Serialize)]
 pub(crate) struct SetupTestOutput {
+    #[serde(skip)]
     header: String,
+    registry: String,
     crates: Vec<String>,
+    /// The bytes written for each crate added, in the order of `crates`
+    #[serde(skip)]
+    crate_bytes: Vec<DiskSize>,
+    /// Each version added, as the crate name and version
+    #[serde(rename = "versions_added")]
+    versions: Vec<String>,
+    /// Crates were added to an existing registry
+    #[serde(skip)]
+    appended: bool,
+    #[serde(rename = "bytes_written")]
     total: DiskSize,
 }
 
+/// A row of the CSV output: a crate added to the registry and the bytes
+/// written for it
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub(crate) struct CrateAddedRecord {
+    registry: String,
+    #[serde(rename = "crate")]
+    name: String,
+    bytes_written: u64,
+}
+
+impl Report for SetupTestOutput {
+    type Record = CrateAddedRecord;
+
+    const CSV_HEADERS: &'static [&'static str] = &["registry", "crate", "bytes_written"];
+
+    fn records(&self) -> Vec<Self::Record> {
+        self.crates
+            .iter()
+            .zip(&self.crate_bytes)
+            .map(|(name, bytes)| CrateAddedRecord {
+                registry: self.registry.clone(),
+                name: name.clone(),
+                bytes_written: bytes.bytes(),
+            })
+            .collect()
+    }
+}
+
 impl Display for SetupTestOutput {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.header)?;
-        if !self.crates.is_empty() {
+        if self.appended {
+            if self.versions.is_empty() {
+                write!(f, "  The registry already holds every version")?;
+            } else {
+                write!(f, "  Versions added:\n    ")?;
+                self.versions.join("\n    ").fmt(f)?;
+            }
+        } else if !self.crates.is_empty() {
             write!(f, "  Crates added:\n    ")?;
             self.crates.join("\n    ").fmt(f)?;
         }
@@ -158,6 +387,7 @@ mod tests {
 
     use std::fmt::Write;
 
+    use tame_index::{KrateName, index::FileLock};
     use tempfile::TempDir;
 
     use super::*;
@@ -210,7 +440,8 @@ mod tests {
         let mut output = SetupTestOutputBuilder::new(index_crate.clone(), registry);
 
         output.initialise_local_registry(false).unwrap();
-        output.insert_crate(&index_crate).unwrap();
+        let index = crate::tests::get_test_index(registry).unwrap();
+        output.insert_crate(&index_crate, &index).unwrap();
         output
     }
 
@@ -223,13 +454,17 @@ mod tests {
         let mut output = SetupTestOutputBuilder::new(index_crate.clone(), new_registry);
 
         output.initialise_local_registry(false).unwrap();
-        output.insert_crate(&index_crate).unwrap();
-
-        let dependencies = index_crate.most_recent_version().dependencies();
-        println!("Dependencies: {dependencies:?}");
-
         let index = crate::tests::get_test_index(local_registry).unwrap();
-        output.add_dependency_crates(dependencies, &index).unwrap();
+        output.insert_crate(&index_crate, &index).unwrap();
+
+        output
+            .add_dependency_crates(
+                &index_crate,
+                &[index_crate.most_recent_version()],
+                &index,
+                WalkOptions::default(),
+            )
+            .unwrap();
         output
     }
 
@@ -338,19 +573,169 @@ mod tests {
         let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
         let mut output = get_output_initialised(&registry, TEST_CRATE_NAME);
         let index_crate = IndexKrate::new(TEST_CRATE).unwrap();
+        let index = crate::tests::get_test_index(&registry).unwrap();
 
-        assert!(output.insert_crate(&index_crate).is_ok());
+        assert!(output.insert_crate(&index_crate, &index).is_ok());
         assert_eq!(output.crates.len(), 1);
         assert_eq!(output.crates[0], "forestry".to_string());
     }
 
+    #[test]
+    fn test_insert_crate_from_alternative_registry() {
+        let (_temp_dir_cache, index) = crate::tests::get_test_sparse_index();
+        let (_temp_dir_new, new_registry) = get_new_temp_registry();
+        let index_crate = IndexKrate::new(TEST_CRATE).unwrap();
+        let mut output = SetupTestOutputBuilder::new(index_crate.clone(), &new_registry);
+        output.initialise_local_registry(false).unwrap();
+
+        assert!(output.insert_crate(&index_crate, &index).is_ok());
+        assert_eq!(output.crates, vec![TEST_CRATE_NAME.to_string()]);
+        assert_eq!(output.total, DiskSize::new(9693));
+    }
+
+    #[test]
+    fn test_insert_crate_from_git_registry() {
+        let (_temp_dir_index, index) = crate::tests::get_test_git_index();
+        let (_temp_dir_new, new_registry) = get_new_temp_registry();
+        let index_crate = IndexKrate::new(TEST_CRATE).unwrap();
+        let mut output = SetupTestOutputBuilder::new(index_crate.clone(), &new_registry);
+        output.initialise_local_registry(false).unwrap();
+
+        assert!(output.insert_crate(&index_crate, &index).is_ok());
+        assert_eq!(output.total, DiskSize::new(9693));
+    }
+
+    #[test]
+    fn test_insert_crate_from_authenticated_registry() {
+        let (_temp_dir_cache, index) =
+            crate::tests::get_authenticated_test_sparse_index(Some("secret-token"));
+        let index = index.unwrap();
+        let (_temp_dir_new, new_registry) = get_new_temp_registry();
+        let index_crate = IndexKrate::new(TEST_CRATE).unwrap();
+        let mut output = SetupTestOutputBuilder::new(index_crate.clone(), &new_registry);
+        output.initialise_local_registry(false).unwrap();
+
+        assert!(output.insert_crate(&index_crate, &index).is_ok());
+        assert_eq!(output.total, DiskSize::new(9693));
+    }
+
+    /// Serves the test registry, returning a sparse index for it, its
+    /// request log and a new registry holding `colored`
+    fn get_registry_with_colored() -> (Vec<TempDir>, ComboIndex, crate::tests::RequestLog, String) {
+        let (url, log) = crate::tests::serve_logged_test_registry(None);
+        let (temp_dir_cache, location) = crate::tests::test_sparse_location(url);
+        let index = crate::get_remote_combo_index(location, None, None).unwrap();
+        let (temp_dir_new, new_registry) = get_new_temp_registry();
+
+        let colored = get_index_crate(crate::tests::TEST_REGISTRY, "colored");
+        let mut output = SetupTestOutputBuilder::new(colored.clone(), &new_registry);
+        output.initialise_local_registry(false).unwrap();
+        output.insert_crate(&colored, &index).unwrap();
+        output.finalize().unwrap();
+
+        (vec![temp_dir_cache, temp_dir_new], index, log, new_registry)
+    }
+
+    fn downloads(log: &crate::tests::RequestLog) -> Vec<String> {
+        log.lock()
+            .unwrap()
+            .iter()
+            .filter(|(path, _)| path.ends_with(".crate"))
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_append_adds_only_new_crates() {
+        let (_temp_dirs, index, log, registry) = get_registry_with_colored();
+        let colored = get_index_crate(crate::tests::TEST_REGISTRY, "colored");
+        let some_crate = get_index_crate(crate::tests::TEST_REGISTRY, TEST_CRATE_NO_DEPENDENCY);
+
+        let mut output = SetupTestOutputBuilder::new(some_crate.clone(), &registry);
+        output.open_local_registry().unwrap();
+        output.insert_crate(&some_crate, &index).unwrap();
+        output.insert_crate(&colored, &index).unwrap();
+        let result = output.finalize().unwrap();
+
+        assert_eq!(result.crates, ["some_crate"]);
+        assert_eq!(result.versions, ["some_crate 0.2.1"]);
+        assert!(result.appended);
+        assert_eq!(result.records()[0].bytes_written, result.total.bytes());
+        assert_eq!(
+            downloads(&log),
+            ["colored-2.1.0.crate", "some_crate-0.2.1.crate"]
+        );
+        let registry_path = PathBuf::from(registry.as_str());
+        assert!(registry_path.join("colored-2.1.0.crate").is_file());
+        assert!(registry_path.join("some_crate-0.2.1.crate").is_file());
+        assert!(!registry_path.join(STAGING_DIR).exists());
+        let merged = get_index_crate(&registry, TEST_CRATE_NO_DEPENDENCY);
+        assert_eq!(merged.versions.len(), 1);
+    }
+
+    #[test]
+    fn test_append_restores_missing_crate_file() {
+        let (_temp_dirs, index, log, registry) = get_registry_with_colored();
+        let colored = get_index_crate(crate::tests::TEST_REGISTRY, "colored");
+        let registry_path = PathBuf::from(registry.as_str());
+        fs::remove_file(registry_path.join("colored-2.1.0.crate")).unwrap();
+
+        let mut output = SetupTestOutputBuilder::new(colored.clone(), &registry);
+        output.open_local_registry().unwrap();
+        output.insert_crate(&colored, &index).unwrap();
+        let result = output.finalize().unwrap();
+
+        assert_eq!(result.versions, ["colored 2.1.0"]);
+        assert_eq!(downloads(&log).len(), 2);
+        let merged = get_index_crate(&registry, "colored");
+        assert_eq!(merged.versions.len(), 1);
+    }
+
+    #[test]
+    fn test_append_creates_missing_registry() {
+        let (_temp_dir_new, registry) = get_new_temp_registry();
+        let index_crate = get_index_crate(crate::tests::TEST_REGISTRY, TEST_CRATE_NAME);
+        let mut output = SetupTestOutputBuilder::new(index_crate, &registry);
+
+        output.open_local_registry().unwrap();
+
+        assert!(output.registry.is_some());
+        assert!(output.existing.is_none());
+    }
+
+    #[test]
+    fn test_fmt_appended() {
+        let mut output = SetupTestOutput {
+            header: String::from("Test Header\n"),
+            registry: String::from("tests/local_registry"),
+            crates: vec![String::from("crate1")],
+            crate_bytes: vec![],
+            versions: vec![String::from("crate1 1.0.0"), String::from("crate1 1.1.0")],
+            appended: true,
+            total: DiskSize::new(200),
+        };
+        let mut result = String::new();
+        write!(&mut result, "{output}").unwrap();
+        assert_eq!(
+            result,
+            "Test Header\n  Versions added:\n    crate1 1.0.0\n    crate1 1.1.0\n  Total bytes written: 200.00 B\n"
+        );
+
+        output.crates.clear();
+        output.versions.clear();
+        let mut result = String::new();
+        write!(&mut result, "{output}").unwrap();
+        assert!(result.contains("The registry already holds every version"));
+    }
+
     #[test]
     fn test_insert_crate_registry_not_set() {
         let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
         let mut output = get_output_new(&registry, TEST_CRATE_NAME);
         let index_crate = IndexKrate::new(TEST_CRATE).unwrap();
+        let index = crate::tests::get_test_index(&registry).unwrap();
 
-        let result = output.insert_crate(&index_crate);
+        let result = output.insert_crate(&index_crate, &index);
         assert!(matches!(result, Err(Error::LocalRegistryBuilderNotSet)));
     }
 
@@ -361,8 +746,12 @@ mod tests {
         output.initialise_local_registry(false).unwrap();
         let index_crate = IndexKrate::new(TEST_CRATE).unwrap();
         let index = crate::tests::get_test_index(&registry).unwrap();
-        let result =
-            output.add_dependency_crates(index_crate.most_recent_version().dependencies(), &index);
+        let result = output.add_dependency_crates(
+            &index_crate,
+            &[index_crate.most_recent_version()],
+            &index,
+            WalkOptions::default(),
+        );
         println!("Result: {result:?}");
 
         assert!(result.is_ok());
@@ -375,8 +764,12 @@ mod tests {
         output.initialise_local_registry(false).unwrap();
         let index_crate = IndexKrate::new(TEST_CRATE).unwrap();
         let index = crate::tests::get_test_index(&registry).unwrap();
-        let result =
-            output.add_dependency_crates(index_crate.most_recent_version().dependencies(), &index);
+        let result = output.add_dependency_crates(
+            &index_crate,
+            &[index_crate.most_recent_version()],
+            &index,
+            WalkOptions::default(),
+        );
         println!("Result: {result:?}");
 
         assert!(result.is_ok());
@@ -391,7 +784,7 @@ mod tests {
 
         let lock = FileLock::unlocked();
 
-        let combo_index_remote = crate::get_remote_combo_index().unwrap();
+        let combo_index_remote = crate::IndexOptions::default().combo_index().unwrap();
         let crate_name = KrateName::crates_io(ONLINE_TEST_CRATE_NAME).unwrap();
         println!("Crates.io crate name: {crate_name:?}");
 
@@ -402,8 +795,12 @@ mod tests {
 
         let index = crate::tests::get_test_index(&registry).unwrap();
 
-        let result =
-            output.add_dependency_crates(index_crate.most_recent_version().dependencies(), &index);
+        let result = output.add_dependency_crates(
+            &index_crate,
+            &[index_crate.most_recent_version()],
+            &index,
+            WalkOptions::default(),
+        );
         println!("Result: {result:?}");
 
         assert!(result.is_ok());
@@ -466,7 +863,11 @@ mod tests {
     fn test_fmt_empty_crates() {
         let output = SetupTestOutput {
             header: String::from("Test Header\n"),
+            registry: String::from("tests/local_registry"),
             crates: vec![],
+            crate_bytes: vec![],
+            versions: vec![],
+            appended: false,
             total: DiskSize::new(100),
         };
         let mut result = String::new();
@@ -478,7 +879,11 @@ mod tests {
     fn test_fmt_single_crate() {
         let output = SetupTestOutput {
             header: String::from("Test Header\n"),
+            registry: String::from("tests/local_registry"),
             crates: vec![String::from("test-crate")],
+            crate_bytes: vec![],
+            versions: vec![],
+            appended: false,
             total: DiskSize::new(50),
         };
         let mut result = String::new();
@@ -493,11 +898,15 @@ mod tests {
     fn test_fmt_multiple_crates() {
         let output = SetupTestOutput {
             header: String::from("Test Header\n"),
+            registry: String::from("tests/local_registry"),
             crates: vec![
                 String::from("crate1"),
                 String::from("crate2"),
                 String::from("crate3"),
             ],
+            crate_bytes: vec![],
+            versions: vec![],
+            appended: false,
             total: DiskSize::new(200),
         };
         let mut result = String::new();
@@ -512,7 +921,11 @@ mod tests {
     fn test_fmt_empty_header() {
         let output = SetupTestOutput {
             header: String::new(),
+            registry: String::from("tests/local_registry"),
             crates: vec![String::from("test-crate")],
+            crate_bytes: vec![],
+            versions: vec![],
+            appended: false,
             total: DiskSize::new(75),
         };
         let mut result = String::new();
@@ -522,4 +935,54 @@ mod tests {
             "  Crates added:\n    test-crate\n  Total bytes written: 75.00 B\n"
         );
     }
+
+    #[test]
+    fn test_records() {
+        let output = SetupTestOutput {
+            header: String::new(),
+            registry: String::from("tests/local_registry"),
+            crates: vec![String::from("crate1"), String::from("crate2")],
+            crate_bytes: vec![DiskSize::new(75), DiskSize::new(125)],
+            versions: vec![],
+            appended: false,
+            total: DiskSize::new(200),
+        };
+
+        let records = output.records();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[1],
+            CrateAddedRecord {
+                registry: String::from("tests/local_registry"),
+                name: String::from("crate2"),
+                bytes_written: 125,
+            }
+        );
+    }
+
+    #[test]
+    fn test_serialise_json() {
+        let output = SetupTestOutput {
+            header: String::from("Test Header\n"),
+            registry: String::from("tests/local_registry"),
+            crates: vec![String::from("test-crate")],
+            crate_bytes: vec![],
+            versions: vec![String::from("test-crate 1.0.0")],
+            appended: false,
+            total: DiskSize::new(75),
+        };
+
+        let value = serde_json::to_value(&output).unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "registry": "tests/local_registry",
+                "crates": ["test-crate"],
+                "versions_added": ["test-crate 1.0.0"],
+                "bytes_written": 75,
+            })
+        );
+    }
 }
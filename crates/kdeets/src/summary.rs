@@ -0,0 +1,318 @@
+//! Structured summary of a crate's entry in a registry index
+
+use semver::{Version, VersionReq};
+use serde::Serialize;
+use tame_index::{IndexKrate, IndexVersion};
+
+use crate::Report;
+
+/// The key versions and every published version of a crate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CrateSummary {
+    /// The name of the crate
+    pub name: String,
+    /// The key versions of the crate
+    #[serde(flatten)]
+    pub key_versions: KeyVersions,
+    /// Every published version, in the order they appear in the index
+    pub versions: Vec<VersionSummary>,
+}
+
+/// The key versions of a crate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeyVersions {
+    /// The first version ever published. May be yanked
+    pub earliest: String,
+    /// The highest version as per semantic versioning
+    pub highest: String,
+    /// The highest version, excluding pre-release and yanked versions
+    pub highest_normal: Option<String>,
+    /// The last version published, even if it is yanked or lower than the
+    /// highest version
+    pub most_recent: String,
+}
+
+/// A published version of a crate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VersionSummary {
+    /// The version number
+    pub version: String,
+    /// Whether the version has been yanked
+    pub yanked: bool,
+    /// The minimum Rust version declared by the version, if any
+    pub rust_version: Option<String>,
+    /// The SHA-256 checksum of the `.crate` file, hex encoded
+    pub checksum: String,
+}
+
+impl CrateSummary {
+    /// Returns a summary of the versions selected by the predicate, with the
+    /// key versions recomputed over them, or `None` if none is selected
+    pub fn filtered(&self, predicate: impl Fn(&VersionSummary) -> bool) -> Option<Self> {
+        let versions = self
+            .versions
+            .iter()
+            .filter(|version| predicate(version))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        Some(Self {
+            name: self.name.clone(),
+            key_versions: KeyVersions {
+                earliest: versions.first()?.version.clone(),
+                highest: highest(versions.iter())?.version.clone(),
+                highest_normal: highest(
+                    versions
+                        .iter()
+                        .filter(|version| !version.yanked && !version.is_prerelease()),
+                )
+                .map(|version| version.version.clone()),
+                most_recent: versions.last()?.version.clone(),
+            },
+            versions,
+        })
+    }
+}
+
+impl VersionSummary {
+    /// Returns `true` if the version satisfies the requirement
+    pub fn matches(&self, req: &VersionReq) -> bool {
+        self.semver().is_some_and(|version| req.matches(&version))
+    }
+
+    /// Returns `true` if the version is a pre-release
+    pub fn is_prerelease(&self) -> bool {
+        self.semver().is_some_and(|version| !version.pre.is_empty())
+    }
+
+    fn semver(&self) -> Option<Version> {
+        Version::parse(&self.version).ok()
+    }
+}
+
+/// Returns the highest of the versions as per semantic versioning
+fn highest<'s>(versions: impl Iterator<Item = &'s VersionSummary>) -> Option<&'s VersionSummary> {
+    versions
+        .filter_map(|version| Some((version.semver()?, version)))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, version)| version)
+}
+
+impl From<&IndexKrate> for CrateSummary {
+    fn from(index_crate: &IndexKrate) -> Self {
+        Self {
+            name: index_crate.name().to_string(),
+            key_versions: KeyVersions::from(index_crate),
+            versions: index_crate
+                .versions
+                .iter()
+                .map(VersionSummary::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<&IndexKrate> for KeyVersions {
+    fn from(index_crate: &IndexKrate) -> Self {
+        Self {
+            earliest: index_crate.earliest_version().version.to_string(),
+            highest: index_crate.highest_version().version.to_string(),
+            highest_normal: index_crate
+                .highest_normal_version()
+                .map(|version| version.version.to_string()),
+            most_recent: index_crate.most_recent_version().version.to_string(),
+        }
+    }
+}
+
+impl From<&IndexVersion> for VersionSummary {
+    fn from(version: &IndexVersion) -> Self {
+        Self {
+            version: version.version.to_string(),
+            yanked: version.yanked,
+            rust_version: version.rust_version.as_ref().map(ToString::to_string),
+            checksum: version.checksum.to_string(),
+        }
+    }
+}
+
+/// A row of the CSV output of a crate summary: a published version with
+/// flags marking the key versions
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub(crate) struct VersionRecord {
+    #[serde(rename = "crate")]
+    name: String,
+    version: String,
+    yanked: bool,
+    rust_version: Option<String>,
+    checksum: String,
+    earliest: bool,
+    highest: bool,
+    highest_normal: bool,
+    most_recent: bool,
+}
+
+impl Report for CrateSummary {
+    type Record = VersionRecord;
+
+    const CSV_HEADERS: &'static [&'static str] = &[
+        "crate",
+        "version",
+        "yanked",
+        "rust_version",
+        "checksum",
+        "earliest",
+        "highest",
+        "highest_normal",
+        "most_recent",
+    ];
+
+    fn records(&self) -> Vec<Self::Record> {
+        self.versions
+            .iter()
+            .map(|version| VersionRecord {
+                name: self.name.clone(),
+                version: version.version.clone(),
+                yanked: version.yanked,
+                rust_version: version.rust_version.clone(),
+                checksum: version.checksum.clone(),
+                earliest: version.version == self.key_versions.earliest,
+                highest: version.version == self.key_versions.highest,
+                highest_normal: self.key_versions.highest_normal.as_ref() == Some(&version.version),
+                most_recent: version.version == self.key_versions.most_recent,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_from_index_crate() {
+        let index_crate = IndexKrate::new(format!(
+            "{}/index/so/me/some_crate",
+            crate::tests::TEST_REGISTRY
+        ))
+        .unwrap();
+
+        let summary = CrateSummary::from(&index_crate);
+
+        assert_eq!(summary.name, "some_crate");
+        assert_eq!(summary.key_versions.earliest, "0.2.1");
+        assert_eq!(summary.key_versions.highest, "0.2.1");
+        assert_eq!(
+            summary.key_versions.highest_normal.as_deref(),
+            Some("0.2.1")
+        );
+        assert_eq!(summary.key_versions.most_recent, "0.2.1");
+        assert_eq!(
+            summary.versions,
+            vec![VersionSummary {
+                version: "0.2.1".to_string(),
+                yanked: false,
+                rust_version: None,
+                checksum: index_crate.versions[0].checksum.to_string(),
+            }]
+        );
+        assert_eq!(summary.versions[0].checksum.len(), 64);
+    }
+
+    fn versions(versions: &[&str]) -> CrateSummary {
+        let versions = versions
+            .iter()
+            .map(|version| VersionSummary {
+                version: version.to_string(),
+                yanked: false,
+                rust_version: None,
+                checksum: String::new(),
+            })
+            .collect::<Vec<_>>();
+        CrateSummary {
+            name: "some_crate".to_string(),
+            key_versions: KeyVersions {
+                earliest: versions[0].version.clone(),
+                highest: versions[0].version.clone(),
+                highest_normal: None,
+                most_recent: versions[0].version.clone(),
+            },
+            versions,
+        }
+    }
+
+    #[test]
+    fn test_filtered_by_requirement() {
+        let summary = versions(&["1.0.0", "1.3.0", "1.2.5", "2.0.0", "1.4.0-rc.1"]);
+
+        let highest = |req: &str| {
+            let req = VersionReq::parse(req).unwrap();
+            summary
+                .filtered(|version| version.matches(&req))
+                .map(|filtered| filtered.key_versions.highest)
+        };
+
+        assert_eq!(highest("^1").as_deref(), Some("1.3.0"));
+        assert_eq!(highest(">=1.2, <1.3").as_deref(), Some("1.2.5"));
+        assert_eq!(highest(">=1.4.0-rc.1, <2").as_deref(), Some("1.4.0-rc.1"));
+        assert_eq!(highest("^3"), None);
+    }
+
+    #[test]
+    fn test_filtered_recomputes_key_versions() {
+        let mut summary = versions(&["1.0.0", "1.3.0", "1.2.5", "2.0.0-rc.1", "1.4.0-rc.1"]);
+        summary.versions[1].yanked = true;
+
+        let stable = summary
+            .filtered(|version| !version.is_prerelease())
+            .unwrap();
+        assert_eq!(stable.key_versions.earliest, "1.0.0");
+        assert_eq!(stable.key_versions.highest, "1.3.0");
+        assert_eq!(stable.key_versions.highest_normal.as_deref(), Some("1.2.5"));
+        assert_eq!(stable.key_versions.most_recent, "1.2.5");
+        assert_eq!(stable.versions.len(), 3);
+
+        let prerelease = summary.filtered(VersionSummary::is_prerelease).unwrap();
+        assert_eq!(prerelease.key_versions.earliest, "2.0.0-rc.1");
+        assert_eq!(prerelease.key_versions.highest, "2.0.0-rc.1");
+        assert_eq!(prerelease.key_versions.highest_normal, None);
+        assert_eq!(prerelease.key_versions.most_recent, "1.4.0-rc.1");
+
+        assert_eq!(summary.filtered(|_| false), None);
+    }
+
+    #[test]
+    fn test_version_matches() {
+        let summary = versions(&["1.2.0", "not-semver"]);
+        let req = VersionReq::parse("^1").unwrap();
+
+        assert!(summary.versions[0].matches(&req));
+        assert!(!summary.versions[1].matches(&req));
+    }
+
+    #[test]
+    fn test_summary_records() {
+        let index_crate = IndexKrate::new(format!(
+            "{}/index/so/me/some_crate",
+            crate::tests::TEST_REGISTRY
+        ))
+        .unwrap();
+
+        let records = CrateSummary::from(&index_crate).records();
+
+        assert_eq!(
+            records,
+            vec![VersionRecord {
+                name: "some_crate".to_string(),
+                version: "0.2.1".to_string(),
+                yanked: false,
+                rust_version: None,
+                checksum: index_crate.versions[0].checksum.to_string(),
+                earliest: true,
+                highest: true,
+                highest_normal: true,
+                most_recent: true,
+            }]
+        );
+    }
+}
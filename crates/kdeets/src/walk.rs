@@ -0,0 +1,291 @@
+//! Walking the dependency graph of crate versions, shared by the Rust version
+//! analysis of `rust` and the dependency closure added by `setup`
+
+use std::collections::{BTreeSet, HashMap};
+use std::rc::Rc;
+
+use semver::{Version, VersionReq};
+use tame_index::krate::{DependencyKind as IndexDependencyKind, IndexDependency};
+use tame_index::{IndexKrate, IndexVersion, KrateName, index::FileLock};
+
+use crate::{ComboIndex, Error};
+
+mod closure;
+
+pub(crate) use closure::{DependencyClosure, Requirement};
+
+/// The dependencies followed when walking the dependency graph, and the
+/// versions each requirement is resolved to.
+///
+/// Normal dependencies are always followed. Dev dependencies are only ever
+/// built for the crate itself, so they are not followed past it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WalkOptions {
+    /// Follow every optional dependency, not only those enabled by features
+    pub(crate) optional: bool,
+    /// Follow the dev dependencies of the crate
+    pub(crate) dev: bool,
+    /// Follow build dependencies
+    pub(crate) build: bool,
+    /// Skip dependencies for these targets, each a target triple, a
+    /// `cfg(...)` predicate or the predicate inside it
+    pub(crate) exclude_targets: Vec<String>,
+    /// Resolve to the lowest matching version rather than the highest
+    pub(crate) minimal_versions: bool,
+    /// Walk every version matching a requirement rather than the one it
+    /// resolves to
+    pub(crate) all_versions: bool,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        Self {
+            optional: false,
+            dev: false,
+            build: true,
+            exclude_targets: vec![],
+            minimal_versions: false,
+            all_versions: false,
+        }
+    }
+}
+
+impl WalkOptions {
+    /// Whether the dependency of a version is followed, given whether the
+    /// version is the crate walked from and the optional dependencies its
+    /// features enable
+    pub(crate) fn follows(
+        &self,
+        dependency: &IndexDependency,
+        root: bool,
+        enabled: &BTreeSet<String>,
+    ) -> bool {
+        let kind = match dependency.kind() {
+            IndexDependencyKind::Normal => true,
+            IndexDependencyKind::Build => self.build,
+            IndexDependencyKind::Dev => root && self.dev,
+        };
+        let optional = !dependency.is_optional()
+            || self.optional
+            || enabled.contains(dependency.name.as_str());
+        let target = dependency
+            .target()
+            .is_none_or(|target| !self.excludes_target(target));
+        kind && optional && target
+    }
+
+    /// Whether dependencies for the target, a target triple or `cfg(...)`
+    /// predicate, are skipped.
+    ///
+    /// A target is only skipped when it is excluded exactly, ignoring
+    /// whitespace, so excluding `windows` skips `cfg(windows)` but not
+    /// `cfg(not(windows))`.
+    pub(crate) fn excludes_target(&self, target: &str) -> bool {
+        let target = without_whitespace(target);
+        self.exclude_targets.iter().any(|excluded| {
+            let excluded = without_whitespace(excluded);
+            target == excluded || target == format!("cfg({excluded})")
+        })
+    }
+
+    /// The versions of the crate walked for a requirement on it: the version
+    /// cargo would resolve it to, or every version, that is not yanked,
+    /// matching it when walking all versions
+    pub(crate) fn resolve<'k>(
+        &self,
+        index_crate: &'k IndexKrate,
+        req: &VersionReq,
+    ) -> Result<Vec<&'k IndexVersion>, Error> {
+        if self.all_versions {
+            return matching_versions(index_crate, req);
+        }
+        Ok(matching_version(index_crate, req, self.minimal_versions)?
+            .into_iter()
+            .collect())
+    }
+}
+
+fn without_whitespace(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// The crates fetched from the index during a walk, each fetched once
+pub(crate) struct IndexCrates<'i> {
+    index: &'i ComboIndex,
+    crates: HashMap<String, Rc<IndexKrate>>,
+    /// The crates fetched, in the order they were first fetched
+    fetched: Vec<String>,
+}
+
+impl<'i> IndexCrates<'i> {
+    pub(crate) fn new(index: &'i ComboIndex) -> Self {
+        Self {
+            index,
+            crates: HashMap::new(),
+            fetched: vec![],
+        }
+    }
+
+    pub(crate) fn index(&self) -> &'i ComboIndex {
+        self.index
+    }
+
+    /// Adds a crate that has already been fetched
+    pub(crate) fn insert(&mut self, index_crate: IndexKrate) -> Rc<IndexKrate> {
+        let index_crate = Rc::new(index_crate);
+        self.crates
+            .insert(index_crate.name().to_string(), Rc::clone(&index_crate));
+        index_crate
+    }
+
+    /// A crate already fetched or added
+    pub(crate) fn get(&self, name: &str) -> Option<&Rc<IndexKrate>> {
+        self.crates.get(name)
+    }
+
+    /// Fetches the crate from the index, or from the crates already fetched
+    pub(crate) fn krate(&mut self, name: &str) -> Result<Option<Rc<IndexKrate>>, Error> {
+        if let Some(index_crate) = self.crates.get(name) {
+            return Ok(Some(Rc::clone(index_crate)));
+        }
+
+        let lock = FileLock::unlocked();
+        let Some(index_crate) = self.index.krate(KrateName::cargo(name)?, true, &lock)? else {
+            return Ok(None);
+        };
+
+        let index_crate = Rc::new(index_crate);
+        self.crates
+            .insert(name.to_string(), Rc::clone(&index_crate));
+        self.fetched.push(name.to_string());
+        Ok(Some(index_crate))
+    }
+
+    /// The names of the crates fetched from the index, in the order they were
+    /// first fetched
+    pub(crate) fn fetched(&self) -> &[String] {
+        &self.fetched
+    }
+}
+
+/// The versions of the crate, that are not yanked, matching the requirement
+pub(crate) fn matching_versions<'k>(
+    index_crate: &'k IndexKrate,
+    req: &VersionReq,
+) -> Result<Vec<&'k IndexVersion>, Error> {
+    Ok(matching(index_crate, req)?
+        .into_iter()
+        .map(|(_, version)| version)
+        .collect())
+}
+
+/// Finds the version of the crate cargo would resolve the requirement to: the
+/// highest matching version that is not yanked, or the lowest when resolving
+/// minimal versions
+pub(crate) fn matching_version<'k>(
+    index_crate: &'k IndexKrate,
+    req: &VersionReq,
+    minimal_versions: bool,
+) -> Result<Option<&'k IndexVersion>, Error> {
+    let matching = matching(index_crate, req)?.into_iter();
+    let selected = if minimal_versions {
+        matching.min_by(|(a, _), (b, _)| a.cmp(b))
+    } else {
+        matching.max_by(|(a, _), (b, _)| a.cmp(b))
+    };
+    Ok(selected.map(|(_, version)| version))
+}
+
+fn matching<'k>(
+    index_crate: &'k IndexKrate,
+    req: &VersionReq,
+) -> Result<Vec<(Version, &'k IndexVersion)>, Error> {
+    let mut matching = vec![];
+    for version in index_crate
+        .versions
+        .iter()
+        .filter(|version| !version.yanked)
+    {
+        let semver = Version::parse(&version.version)?;
+        if req.matches(&semver) {
+            matching.push((semver, version));
+        }
+    }
+    Ok(matching)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching_versions() {
+        let index_crate = crate::diff::tests::two_versions();
+        let req = VersionReq::parse(">=1.2").unwrap();
+
+        let matching = matching_versions(&index_crate, &req).unwrap();
+        assert_eq!(matching.len(), 2);
+
+        let highest = matching_version(&index_crate, &req, false).unwrap();
+        assert_eq!(highest.unwrap().version, "1.3.0");
+        let lowest = matching_version(&index_crate, &req, true).unwrap();
+        assert_eq!(lowest.unwrap().version, "1.2.0");
+
+        let req = VersionReq::parse("^2").unwrap();
+        assert!(matching_versions(&index_crate, &req).unwrap().is_empty());
+        assert!(
+            matching_version(&index_crate, &req, false)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_excludes_target() {
+        let options = WalkOptions {
+            exclude_targets: vec![
+                "windows".to_string(),
+                "cfg(target_os = \"linux\")".to_string(),
+                "wasm32-unknown-unknown".to_string(),
+            ],
+            ..WalkOptions::default()
+        };
+
+        assert!(options.excludes_target("cfg(windows)"));
+        assert!(options.excludes_target("cfg(target_os=\"linux\")"));
+        assert!(options.excludes_target("wasm32-unknown-unknown"));
+        assert!(!options.excludes_target("cfg(not(windows))"));
+        assert!(!options.excludes_target("cfg(any(windows, unix))"));
+        assert!(!options.excludes_target("x86_64-pc-windows-msvc"));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let index_crate = crate::diff::tests::two_versions();
+        let req = VersionReq::parse("^1").unwrap();
+        let versions = |options: WalkOptions| {
+            options
+                .resolve(&index_crate, &req)
+                .unwrap()
+                .iter()
+                .map(|version| version.version.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(versions(WalkOptions::default()), ["1.3.0"]);
+        assert_eq!(
+            versions(WalkOptions {
+                minimal_versions: true,
+                ..WalkOptions::default()
+            }),
+            ["1.2.0"]
+        );
+        assert_eq!(
+            versions(WalkOptions {
+                all_versions: true,
+                ..WalkOptions::default()
+            }),
+            ["1.2.0", "1.3.0"]
+        );
+    }
+}
@@ -0,0 +1,459 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::rc::Rc;
+
+use semver::VersionReq;
+use tame_index::{IndexKrate, IndexVersion};
+
+use crate::features::EnabledFeatures;
+use crate::walk::{IndexCrates, WalkOptions};
+use crate::{ComboIndex, Error, FeatureSummary};
+
+/// A requirement on a crate, such as a dependency declared in a manifest,
+/// with the features it asks for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Requirement {
+    pub(crate) name: String,
+    pub(crate) req: VersionReq,
+    pub(crate) features: BTreeSet<String>,
+    pub(crate) default_features: bool,
+}
+
+/// The features a crate version is walked with
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Features {
+    default: bool,
+    requested: BTreeSet<String>,
+}
+
+impl Features {
+    /// Adds the features, returning whether any were new
+    fn merge(&mut self, other: &Features) -> bool {
+        let before = (self.default, self.requested.len());
+        self.default |= other.default;
+        self.requested.extend(other.requested.iter().cloned());
+        before != (self.default, self.requested.len())
+    }
+}
+
+/// Collects every crate version in the dependency graph of some versions of
+/// a crate: for each dependency followed, the versions the options resolve
+/// its requirement to.
+///
+/// Each crate is fetched from the index once. A crate version is walked again
+/// only when it is reached with features it was not yet walked with, as those
+/// may enable more optional dependencies.
+pub(crate) struct DependencyClosure<'i> {
+    options: WalkOptions,
+    crates: IndexCrates<'i>,
+    selected: HashMap<String, BTreeSet<String>>,
+    walked: HashMap<(String, String), Features>,
+    pending: VecDeque<(Rc<IndexKrate>, String, bool)>,
+}
+
+impl<'i> DependencyClosure<'i> {
+    pub(crate) fn new(index: &'i ComboIndex, options: WalkOptions) -> Self {
+        Self::with_crates(IndexCrates::new(index), options)
+    }
+
+    /// Walks with the crates already fetched
+    pub(crate) fn with_crates(crates: IndexCrates<'i>, options: WalkOptions) -> Self {
+        Self {
+            options,
+            crates,
+            selected: HashMap::new(),
+            walked: HashMap::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Walks the dependency graph of the versions of the crate with their
+    /// default features
+    pub(crate) fn walk(
+        &mut self,
+        index_crate: &IndexKrate,
+        versions: &[&IndexVersion],
+    ) -> Result<(), Error> {
+        let index_crate = self.crates.insert(index_crate.clone());
+        let features = Features {
+            default: true,
+            requested: BTreeSet::new(),
+        };
+        for version in versions {
+            self.select(&index_crate, version, &features, true);
+        }
+
+        self.walk_pending()
+    }
+
+    /// Walks the dependency graph of the version selected for each
+    /// requirement, as the dependencies of a crate that is not on the index.
+    pub(crate) fn walk_requirements(&mut self, requirements: &[Requirement]) -> Result<(), Error> {
+        for requirement in requirements {
+            let Some(index_crate) = self.crates.krate(&requirement.name)? else {
+                return Err(self.crates.index().not_found(&requirement.name));
+            };
+            let features = Features {
+                default: requirement.default_features,
+                requested: requirement.features.clone(),
+            };
+            if !self.require(&index_crate, &requirement.req, &features)? {
+                return Err(Error::NoMatchingVersion(
+                    requirement.name.clone(),
+                    format!("requirement {}", requirement.req),
+                ));
+            }
+        }
+
+        self.walk_pending()
+    }
+
+    fn walk_pending(&mut self) -> Result<(), Error> {
+        while let Some((index_crate, version, root)) = self.pending.pop_front() {
+            let Some(version) = index_crate
+                .versions
+                .iter()
+                .find(|index_version| index_version.version == version.as_str())
+            else {
+                continue;
+            };
+            self.walk_version(version, root)?;
+        }
+        Ok(())
+    }
+
+    /// The crates found other than the crate walked, in the order they were
+    /// found, each with the versions selected
+    pub(crate) fn dependencies(&self) -> impl Iterator<Item = (&IndexKrate, Vec<&IndexVersion>)> {
+        self.crates.fetched().iter().filter_map(|name| {
+            let index_crate = self.crates.get(name)?;
+            let selected = self.selected.get(name)?;
+            let versions = index_crate
+                .versions
+                .iter()
+                .filter(|version| selected.contains(version.version.as_str()))
+                .collect();
+            Some((index_crate.as_ref(), versions))
+        })
+    }
+
+    /// Records the version as selected and queues it to be walked if it has
+    /// not been walked with the features
+    fn select(
+        &mut self,
+        index_crate: &Rc<IndexKrate>,
+        version: &IndexVersion,
+        features: &Features,
+        root: bool,
+    ) {
+        let name = index_crate.name();
+        self.selected
+            .entry(name.to_string())
+            .or_default()
+            .insert(version.version.to_string());
+
+        let key = (name.to_string(), version.version.to_string());
+        let changed = match self.walked.get_mut(&key) {
+            Some(walked) => walked.merge(features),
+            None => {
+                self.walked.insert(key, features.clone());
+                true
+            }
+        };
+        if changed {
+            self.pending
+                .push_back((Rc::clone(index_crate), version.version.to_string(), root));
+        }
+    }
+
+    fn walk_version(&mut self, version: &IndexVersion, root: bool) -> Result<(), Error> {
+        let enabled = self.enabled(version);
+
+        for dependency in version.dependencies() {
+            if !self
+                .options
+                .follows(dependency, root, &enabled.dependencies)
+            {
+                continue;
+            }
+            let name = dependency.crate_name();
+            let Some(index_crate) = self.crates.krate(name)? else {
+                log::warn!("Could not find dependency: {name}, skipping.");
+                continue;
+            };
+
+            let mut requested = dependency
+                .features()
+                .iter()
+                .cloned()
+                .collect::<BTreeSet<_>>();
+            if let Some(dependency_features) =
+                enabled.dependency_features.get(dependency.name.as_str())
+            {
+                requested.extend(dependency_features.iter().cloned());
+            }
+            let features = Features {
+                default: dependency.has_default_features(),
+                requested,
+            };
+
+            let req: VersionReq = dependency.version_requirement();
+            if !self.require(&index_crate, &req, &features)? {
+                log::warn!("No version of dependency {name} matches {req}, skipping.");
+            }
+        }
+        Ok(())
+    }
+
+    /// What the features the crate version is walked with turn on, nothing
+    /// when the version has not been walked
+    pub(crate) fn enabled(&self, version: &IndexVersion) -> EnabledFeatures {
+        let key = (version.name.to_string(), version.version.to_string());
+        let Some(features) = self.walked.get(&key) else {
+            return EnabledFeatures::default();
+        };
+        FeatureSummary::from(version).enable(&features.requested, features.default)
+    }
+
+    /// The crates fetched during the walk
+    pub(crate) fn crates(&mut self) -> &mut IndexCrates<'i> {
+        &mut self.crates
+    }
+
+    /// The crates fetched during the walk
+    pub(crate) fn into_crates(self) -> IndexCrates<'i> {
+        self.crates
+    }
+
+    /// Whether every published version of the crates found is wanted, not
+    /// only the versions selected
+    pub(crate) fn all_versions(&self) -> bool {
+        self.options.all_versions
+    }
+
+    /// Selects the versions of the crate the options resolve the requirement
+    /// to, returning whether there were any
+    fn require(
+        &mut self,
+        index_crate: &Rc<IndexKrate>,
+        req: &VersionReq,
+        features: &Features,
+    ) -> Result<bool, Error> {
+        let resolved = self.options.resolve(index_crate, req)?;
+        for version in &resolved {
+            self.select(index_crate, version, features, false);
+        }
+        Ok(!resolved.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tame_index::{KrateName, index::FileLock};
+
+    use super::*;
+
+    fn dependency(name: &str, req: &str, kind: &str, optional: bool, extra: &str) -> String {
+        format!(
+            r#"{{"name":"{name}","req":"{req}","features":[],"optional":{optional},"default_features":true,"target":null,"kind":"{kind}"{extra}}}"#
+        )
+    }
+
+    fn version(name: &str, version: &str, yanked: bool, deps: &[String], features: &str) -> String {
+        format!(
+            r#"{{"name":"{name}","vers":"{version}","deps":[{}],"cksum":"0000000000000000000000000000000000000000000000000000000000000000","features":{features},"yanked":{yanked}}}"#,
+            deps.join(",")
+        )
+    }
+
+    /// Index entries for `app`, which has an optional, a dev, a build and a
+    /// windows only dependency and needs the `extra` feature of `lib`, which
+    /// enables `deep`, which depends back on `lib`
+    fn write_entries(registry: &str) {
+        let write = |path: &str, entries: &[String]| {
+            let path = std::path::Path::new(registry).join("index").join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, entries.join("\n")).unwrap();
+        };
+        let no_features = "{}";
+
+        write(
+            "3/a/app",
+            &[version(
+                "app",
+                "1.0.0",
+                false,
+                &[
+                    dependency("lib", "^1", "normal", false, "")
+                        .replace(r#""features":[]"#, r#""features":["extra"]"#),
+                    dependency("opt", "^1", "normal", true, ""),
+                    dependency("dev", "^1", "dev", false, ""),
+                    dependency("bld", "^1", "build", false, ""),
+                    dependency("win", "^1", "normal", false, "")
+                        .replace(r#""target":null"#, r#""target":"cfg(windows)""#),
+                ],
+                no_features,
+            )],
+        );
+        let lib = |number: &str, yanked: bool| {
+            version(
+                "lib",
+                number,
+                yanked,
+                &[dependency("deep", "^1", "normal", true, "")],
+                r#"{"extra":["dep:deep"]}"#,
+            )
+        };
+        write(
+            "3/l/lib",
+            &[
+                lib("1.0.0", false),
+                lib("1.1.0", false),
+                lib("1.2.0", true),
+                lib("2.0.0", false),
+            ],
+        );
+        write(
+            "de/ep/deep",
+            &[version(
+                "deep",
+                "1.0.0",
+                false,
+                &[dependency("lib", "^1.1", "normal", false, "")],
+                no_features,
+            )],
+        );
+        for name in ["opt", "dev", "bld", "win"] {
+            write(
+                &format!("3/{}/{name}", &name[..1]),
+                &[version(name, "1.0.0", false, &[], no_features)],
+            );
+        }
+    }
+
+    /// The crates and versions in the dependency closure of `app`
+    fn closure(options: WalkOptions) -> Vec<(String, Vec<String>)> {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        write_entries(&registry);
+        let index = crate::tests::get_test_index(&registry).unwrap();
+        let lock = FileLock::unlocked();
+        let app = index
+            .krate(KrateName::crates_io("app").unwrap(), true, &lock)
+            .unwrap()
+            .unwrap();
+
+        let mut closure = DependencyClosure::new(&index, options);
+        closure.walk(&app, &[app.most_recent_version()]).unwrap();
+
+        closure
+            .dependencies()
+            .map(|(index_crate, versions)| {
+                (
+                    index_crate.name().to_string(),
+                    versions
+                        .iter()
+                        .map(|version| version.version.to_string())
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    fn names(closure: &[(String, Vec<String>)]) -> Vec<&str> {
+        closure.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_closure_follows_enabled_features() {
+        let closure = closure(WalkOptions::default());
+
+        assert_eq!(names(&closure), ["lib", "bld", "win", "deep"]);
+        assert_eq!(closure[0].1, ["1.1.0"]);
+    }
+
+    #[test]
+    fn test_closure_of_all_versions() {
+        let closure = closure(WalkOptions {
+            all_versions: true,
+            ..WalkOptions::default()
+        });
+
+        assert_eq!(names(&closure), ["lib", "bld", "win", "deep"]);
+        assert_eq!(closure[0].1, ["1.0.0", "1.1.0"]);
+    }
+
+    #[test]
+    fn test_closure_options() {
+        let all = closure(WalkOptions {
+            optional: true,
+            dev: true,
+            ..WalkOptions::default()
+        });
+        assert_eq!(names(&all), ["lib", "opt", "dev", "bld", "win", "deep"]);
+
+        let trimmed = closure(WalkOptions {
+            build: false,
+            exclude_targets: vec!["windows".to_string()],
+            ..WalkOptions::default()
+        });
+        assert_eq!(names(&trimmed), ["lib", "deep"]);
+    }
+
+    #[test]
+    fn test_closure_of_requirements() {
+        let (_temp_dir, registry) = crate::tests::get_temp_local_registry();
+        write_entries(&registry);
+        let index = crate::tests::get_test_index(&registry).unwrap();
+        let requirement = |name: &str, req: &str, features: &[&str]| Requirement {
+            name: name.to_string(),
+            req: VersionReq::parse(req).unwrap(),
+            features: features.iter().map(|feature| feature.to_string()).collect(),
+            default_features: true,
+        };
+
+        let mut closure = DependencyClosure::new(&index, WalkOptions::default());
+        closure
+            .walk_requirements(&[
+                requirement("lib", "=1.0.0", &["extra"]),
+                requirement("bld", "^1", &[]),
+            ])
+            .unwrap();
+        let found = closure
+            .dependencies()
+            .map(|(index_crate, versions)| (index_crate.name().to_string(), versions.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("lib".to_string(), 2),
+                ("bld".to_string(), 1),
+                ("deep".to_string(), 1)
+            ]
+        );
+
+        let mut closure = DependencyClosure::new(&index, WalkOptions::default());
+        let result = closure.walk_requirements(&[requirement("lib", "^3", &[])]);
+        assert!(matches!(result, Err(Error::NoMatchingVersion(name, _)) if name == "lib"));
+    }
+
+    #[test]
+    fn test_closure_of_forestry() {
+        let index = crate::tests::get_test_index(crate::tests::TEST_REGISTRY).unwrap();
+        let lock = FileLock::unlocked();
+        let forestry = index
+            .krate(KrateName::crates_io("forestry").unwrap(), true, &lock)
+            .unwrap()
+            .unwrap();
+
+        let mut closure = DependencyClosure::new(&index, WalkOptions::default());
+        closure
+            .walk(&forestry, &[forestry.most_recent_version()])
+            .unwrap();
+
+        let names = closure
+            .dependencies()
+            .map(|(index_crate, _)| index_crate.name().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names.len(), 11);
+        assert_eq!(names[..3], ["colored", "lazy_static", "windows-sys"]);
+    }
+}
@@ -0,0 +1,50 @@
+use kdeets_lib::{Error, Kdeets, KeyVersions};
+
+const TEST_REGISTRY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/registry");
+
+#[test]
+fn test_registry_version_exists() {
+    let kdeets = Kdeets::from_local_registry(TEST_REGISTRY).unwrap();
+    assert!(kdeets.version_exists("some_crate", "0.2.1").unwrap());
+    assert!(!kdeets.version_exists("some_crate", "0.2.2").unwrap());
+}
+
+#[test]
+fn test_registry_list_versions() {
+    let kdeets = Kdeets::from_local_registry(TEST_REGISTRY).unwrap();
+    let versions = kdeets.list_versions("some_crate").unwrap();
+    assert_eq!(versions, vec!["0.2.1"]);
+}
+
+#[test]
+fn test_registry_key_versions() {
+    let kdeets = Kdeets::from_local_registry(TEST_REGISTRY).unwrap();
+    let key_versions = kdeets.key_versions("forestry").unwrap();
+    assert_eq!(
+        key_versions,
+        KeyVersions {
+            earliest: "1.4.1".to_string(),
+            highest_normal: Some("1.4.1".to_string()),
+            highest: "1.4.1".to_string(),
+            most_recent: "1.4.1".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_registry_crate_not_found() {
+    let kdeets = Kdeets::from_local_registry(TEST_REGISTRY).unwrap();
+    let result = kdeets.list_versions("not_in_this_registry");
+    assert!(matches!(result, Err(Error::CrateNotFoundOnIndex)));
+}
+
+#[test]
+fn test_registry_diff() {
+    let kdeets = Kdeets::from_local_registry(TEST_REGISTRY).unwrap();
+    let diff = kdeets.diff("forestry", "1.4.1", "1.4.1").unwrap();
+    assert!(diff.is_empty());
+    assert!(matches!(
+        kdeets.diff("forestry", "1.4.0", "1.4.1"),
+        Err(Error::VersionNotFound(_, _))
+    ));
+}